@@ -0,0 +1,44 @@
+use tpm2::{
+    commands::GetCapability,
+    os::default_tpm,
+    types::{tpm, tpms::CapabilityData},
+    TpmRun,
+};
+
+fn main() {
+    let mut tpm = default_tpm().expect("Unable to open TPM");
+
+    println!("Comparing the TPM's command attributes with the spec");
+    let mut property = 0;
+    let (mut supported, mut mismatched) = (0, 0);
+    loop {
+        let rsp = tpm
+            .run(GetCapability {
+                capability: tpm::Cap::Commands,
+                property,
+                property_count: 256,
+            })
+            .expect("TPM2_GetCapability failed");
+        let CapabilityData::Commands(list) = rsp.capability_data else {
+            panic!("TPM returned the wrong capability");
+        };
+        for attrs in list {
+            let cc = attrs.command_code();
+            property = cc.get() + 1;
+            supported += 1;
+            match cc.attributes() {
+                None => println!("{cc:?}: unknown command ({attrs:?})"),
+                Some(spec) if spec != attrs => {
+                    mismatched += 1;
+                    println!("{cc:?}: TPM reports {attrs:?}");
+                    println!("    but the spec says {spec:?}");
+                }
+                Some(_) => {}
+            }
+        }
+        if !rsp.more_data {
+            break;
+        }
+    }
+    println!("{supported} commands supported, {mismatched} differ from the spec");
+}
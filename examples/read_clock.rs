@@ -5,5 +5,10 @@ fn main() {
 
     println!("Reading the current Clock");
     let rsp = tpm.run(ReadClock {}).expect("TPM2_ReadClock failed");
-    println!("{:?}", rsp.current_time);
+    let time = rsp.current_time;
+    let clock = time.clock_info;
+    println!("Time:  {:?} since last reset/restart", time.time_duration());
+    println!("Clock: {:?} (safe: {})", clock.clock_duration(), clock.safe);
+    println!("Reset count:   {}", clock.reset_count);
+    println!("Restart count: {}", clock.restart_count);
 }
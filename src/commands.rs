@@ -3,7 +3,7 @@
 //! These structures should be fairly direct translations of the
 //! "TPM2_* Command" and "TPM2_* Response" tables in Part 3 of the TPM2 Spec.
 //!
-//! Of the 117 TPM2 commands, 5 are implemented.
+//! Of the 117 TPM2 commands, 46 are implemented.
 //! If a command is not implemented, there will be a skeleton of code and doc
 //! comments which are commented out.
 //!
@@ -13,7 +13,7 @@
 use crate::{
     error::{MarshalError, UnmarshalError},
     marshal::{CommandData, ResponseData},
-    types::{tpm, tpm2b, tpml, tpms, tpmt, Handle},
+    types::{tpm, tpm2b, tpmi, tpml, tpms, tpmt, Auth, AuthHandle, Handle},
     Auths, Command, Marshal, Unmarshal,
 };
 
@@ -26,9 +26,7 @@ pub struct Startup {
     pub startup_type: tpm::SU,
 }
 impl CommandData for Startup {
-    fn marshal_params(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.startup_type.marshal(buf)
-    }
+    marshal_fields!(params: Startup { startup_type });
 }
 impl Command for Startup {
     const CODE: tpm::CC = tpm::CC::Startup;
@@ -45,9 +43,7 @@ pub struct Shutdown {
     pub shutdown_type: tpm::SU,
 }
 impl CommandData for Shutdown {
-    fn marshal_params(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.shutdown_type.marshal(buf)
-    }
+    marshal_fields!(params: Shutdown { shutdown_type });
 }
 impl Command for Shutdown {
     const CODE: tpm::CC = tpm::CC::Shutdown;
@@ -55,53 +51,84 @@ impl Command for Shutdown {
 }
 impl Auths<0> for Shutdown {}
 
-// /// TPM2_SelfTest Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 10.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct SelfTest {
-//     pub todo: (),
-// }
-// /// TPM2_SelfTest Response
-// ///
-// /// See [SelfTest] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct SelfTestResponse {
-//     pub todo: (),
-// }
+/// TPM2_SelfTest Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 10.2
+///
+/// If `full_test` is false, only untested algorithms are tested. See
+/// [`TpmExt::ensure_tested`](crate::TpmExt::ensure_tested) to test specific
+/// algorithms before using them.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SelfTest {
+    pub full_test: bool,
+}
+impl CommandData for SelfTest {
+    marshal_fields!(params: SelfTest { full_test });
+}
+impl Command for SelfTest {
+    const CODE: tpm::CC = tpm::CC::SelfTest;
+    type Response<'t> = ();
+}
+impl Auths<0> for SelfTest {}
 
-// /// TPM2_IncrementalSelfTest Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 10.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct IncrementalSelfTest {
-//     pub todo: (),
-// }
-// /// TPM2_IncrementalSelfTest Response
-// ///
-// /// See [IncrementalSelfTest] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct IncrementalSelfTestResponse {
-//     pub todo: (),
-// }
+/// TPM2_IncrementalSelfTest Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 10.3
+#[derive(Clone, Copy, Default, Debug)]
+pub struct IncrementalSelfTest<'b> {
+    pub to_test: tpml::AlgIn<'b>,
+}
+impl CommandData for IncrementalSelfTest<'_> {
+    marshal_fields!(params: IncrementalSelfTest { to_test });
+}
+impl Command for IncrementalSelfTest<'_> {
+    const CODE: tpm::CC = tpm::CC::IncrementalSelfTest;
+    type Response<'t> = IncrementalSelfTestResponse<'t>;
+}
+impl Auths<0> for IncrementalSelfTest<'_> {}
 
-// /// TPM2_GetTestResult Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 10.4
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct GetTestResult {
-//     pub todo: (),
-// }
-// /// TPM2_GetTestResult Response
-// ///
-// /// See [GetTestResult] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct GetTestResultResponse {
-//     pub todo: (),
-// }
+/// TPM2_IncrementalSelfTest Response
+///
+/// See [IncrementalSelfTest] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct IncrementalSelfTestResponse<'t> {
+    /// Algorithms that still need to be tested
+    pub to_do_list: tpml::AlgOut<'t>,
+}
+impl<'t> ResponseData<'t> for IncrementalSelfTestResponse<'t> {
+    unmarshal_fields!('t, params: IncrementalSelfTestResponse { to_do_list });
+}
+
+/// TPM2_GetTestResult Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 10.4
+#[derive(Clone, Copy, Default, Debug)]
+pub struct GetTestResult {}
+
+impl CommandData for GetTestResult {}
+impl Command for GetTestResult {
+    const CODE: tpm::CC = tpm::CC::GetTestResult;
+    type Response<'t> = GetTestResultResponse<'t>;
+}
+impl Auths<0> for GetTestResult {}
+
+/// TPM2_GetTestResult Response
+///
+/// See [GetTestResult] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct GetTestResultResponse<'t> {
+    /// Manufacturer-specific information about the test results
+    pub out_data: &'t [u8],
+    /// `None` if testing succeeded, [`tpm::rc::TESTING`] if tests are still
+    /// running, or the error that caused the TPM to enter failure mode
+    pub test_result: tpm::RC,
+}
+impl<'t> ResponseData<'t> for GetTestResultResponse<'t> {
+    unmarshal_fields!('t, params: GetTestResultResponse { out_data, test_result });
+}
 
 // /// TPM2_StartAuthSession Command
 // ///
@@ -192,9 +219,7 @@ pub struct ReadPublic {
     pub object_handle: Handle,
 }
 impl CommandData for ReadPublic {
-    fn marshal_handles(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.object_handle.marshal(buf)
-    }
+    marshal_fields!(handles: ReadPublic { object_handle });
 }
 impl Command for ReadPublic {
     const CODE: tpm::CC = tpm::CC::ReadPublic;
@@ -212,11 +237,7 @@ pub struct ReadPublicResponse<'t> {
     pub qualified_name: Option<tpm2b::Name>,
 }
 impl<'t> ResponseData<'t> for ReadPublicResponse<'t> {
-    fn unmarshal_params(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
-        self.public.unmarshal(buf)?;
-        self.name.unmarshal(buf)?;
-        self.qualified_name.unmarshal(buf)
-    }
+    unmarshal_fields!('t, params: ReadPublicResponse { public, name, qualified_name });
 }
 
 // /// TPM2_ActivateCredential Command
@@ -516,9 +537,7 @@ pub struct GetRandom {
     pub bytes_requested: u16,
 }
 impl CommandData for GetRandom {
-    fn marshal_params(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.bytes_requested.marshal(buf)
-    }
+    marshal_fields!(params: GetRandom { bytes_requested });
 }
 impl Command for GetRandom {
     const CODE: tpm::CC = tpm::CC::GetRandom;
@@ -534,9 +553,7 @@ pub struct GetRandomResponse<'t> {
     pub random_bytes: &'t [u8],
 }
 impl<'t> ResponseData<'t> for GetRandomResponse<'t> {
-    fn unmarshal_params(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
-        self.random_bytes.unmarshal(buf)
-    }
+    unmarshal_fields!('t, params: GetRandomResponse { random_bytes });
 }
 
 // /// TPM2_StirRandom Command
@@ -747,37 +764,89 @@ impl<'t> ResponseData<'t> for GetRandomResponse<'t> {
 //     pub todo: (),
 // }
 
-// /// TPM2_Commit Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 19.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct Commit {
-//     pub todo: (),
-// }
-// /// TPM2_Commit Response
-// ///
-// /// See [Commit] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct CommitResponse {
-//     pub todo: (),
-// }
+/// TPM2_Commit Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 19.2
+///
+/// This is the first phase of an ECDAA signature. The returned `counter` is
+/// passed to [`Sign`] via [`tpmt::SigScheme::Ecdaa`]. The host-side math for
+/// computing `p1` and `y2` on BN_P256 is in [`ecdaa`](crate::ecdaa).
+#[derive(Clone, Copy, Debug)]
+pub struct Commit<'b> {
+    pub sign_handle: AuthHandle<'b>,
+    pub p1: Option<&'b tpm2b::EccPointIn<'b>>,
+    pub s2: &'b [u8],
+    pub y2: &'b [u8],
+}
+impl CommandData for Commit<'_> {
+    marshal_fields!(handles: Commit { sign_handle.handle });
+    marshal_fields!(params: Commit { p1, s2, y2 });
+}
+impl Command for Commit<'_> {
+    const CODE: tpm::CC = tpm::CC::Commit;
+    type Response<'t> = CommitResponse<'t>;
+}
+impl Auths<1> for Commit<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.sign_handle.auth]
+    }
+}
 
-// /// TPM2_EC_Ephemeral Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 19.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct EcEphemeral {
-//     pub todo: (),
-// }
-// /// TPM2_EC_Ephemeral Response
-// ///
-// /// See [EcEphemeral] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct EcEphemeralResponse {
-//     pub todo: (),
-// }
+/// TPM2_Commit Response
+///
+/// See [Commit] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CommitResponse<'t> {
+    pub k: Option<tpm2b::EccPointOut<'t>>,
+    pub l: Option<tpm2b::EccPointOut<'t>>,
+    pub e: tpm2b::EccPointOut<'t>,
+    pub counter: u16,
+}
+impl<'t> ResponseData<'t> for CommitResponse<'t> {
+    unmarshal_fields!('t, params: CommitResponse { k, l, e, counter });
+}
+impl CommitResponse<'_> {
+    /// The signing scheme to use with [`Sign`] to complete this commitment
+    pub const fn sig_scheme(&self, hash: tpmi::AlgHash) -> tpmt::SigScheme {
+        tpmt::SigScheme::Ecdaa(hash, self.counter)
+    }
+}
+
+/// TPM2_EC_Ephemeral Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 19.3
+#[derive(Clone, Copy, Default, Debug)]
+pub struct EcEphemeral {
+    pub curve_id: tpm::EccCurve,
+}
+impl CommandData for EcEphemeral {
+    marshal_fields!(params: EcEphemeral { curve_id });
+}
+impl Command for EcEphemeral {
+    const CODE: tpm::CC = tpm::CC::EcEphemeral;
+    type Response<'t> = EcEphemeralResponse<'t>;
+}
+impl Auths<0> for EcEphemeral {}
+
+/// TPM2_EC_Ephemeral Response
+///
+/// See [EcEphemeral] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct EcEphemeralResponse<'t> {
+    pub q: tpm2b::EccPointOut<'t>,
+    pub counter: u16,
+}
+impl<'t> ResponseData<'t> for EcEphemeralResponse<'t> {
+    unmarshal_fields!('t, params: EcEphemeralResponse { q, counter });
+}
+impl EcEphemeralResponse<'_> {
+    /// The signing scheme to use with [`Sign`] to consume this ephemeral key
+    pub const fn sig_scheme(&self, hash: tpmi::AlgHash) -> tpmt::SigScheme {
+        tpmt::SigScheme::Ecdaa(hash, self.counter)
+    }
+}
 
 // /// TPM2_VerifySignature Command
 // ///
@@ -795,21 +864,41 @@ impl<'t> ResponseData<'t> for GetRandomResponse<'t> {
 //     pub todo: (),
 // }
 
-// /// TPM2_Sign Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 20.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct Sign {
-//     pub todo: (),
-// }
-// /// TPM2_Sign Response
-// ///
-// /// See [Sign] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct SignResponse {
-//     pub todo: (),
-// }
+/// TPM2_Sign Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 20.2
+#[derive(Clone, Copy, Debug)]
+pub struct Sign<'b> {
+    pub key_handle: AuthHandle<'b>,
+    pub digest: &'b [u8],
+    pub in_scheme: Option<tpmt::SigScheme>,
+    pub validation: tpmt::TkHashcheck<'b>,
+}
+impl CommandData for Sign<'_> {
+    marshal_fields!(handles: Sign { key_handle.handle });
+    marshal_fields!(params: Sign { digest, in_scheme, validation });
+}
+impl Command for Sign<'_> {
+    const CODE: tpm::CC = tpm::CC::Sign;
+    type Response<'t> = SignResponse<'t>;
+}
+impl Auths<1> for Sign<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.key_handle.auth]
+    }
+}
+
+/// TPM2_Sign Response
+///
+/// See [Sign] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SignResponse<'t> {
+    pub signature: Option<tpmt::Signature<'t>>,
+}
+impl<'t> ResponseData<'t> for SignResponse<'t> {
+    unmarshal_fields!('t, params: SignResponse { signature });
+}
 
 // /// TPM2_SetCommandCodeAuditStatus Command
 // ///
@@ -827,37 +916,62 @@ impl<'t> ResponseData<'t> for GetRandomResponse<'t> {
 //     pub todo: (),
 // }
 
-// /// TPM2_PCR_Extend Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 22.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct PcrExtend {
-//     pub todo: (),
-// }
-// /// TPM2_PCR_Extend Response
-// ///
-// /// See [PcrExtend] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct PcrExtendResponse {
-//     pub todo: (),
-// }
+/// TPM2_PCR_Extend Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 22.2
+#[derive(Clone, Copy, Debug)]
+pub struct PcrExtend<'b> {
+    pub pcr_handle: AuthHandle<'b>,
+    pub digests: tpml::DigestValuesIn<'b>,
+}
+impl CommandData for PcrExtend<'_> {
+    marshal_fields!(handles: PcrExtend { pcr_handle.handle });
+    marshal_fields!(params: PcrExtend { digests });
+}
+impl Command for PcrExtend<'_> {
+    const CODE: tpm::CC = tpm::CC::PcrExtend;
+    type Response<'t> = ();
+}
+impl Auths<1> for PcrExtend<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.pcr_handle.auth]
+    }
+}
 
-// /// TPM2_PCR_Event Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 22.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct PcrEvent {
-//     pub todo: (),
-// }
-// /// TPM2_PCR_Event Response
-// ///
-// /// See [PcrEvent] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct PcrEventResponse {
-//     pub todo: (),
-// }
+/// TPM2_PCR_Event Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 22.3
+#[derive(Clone, Copy, Debug)]
+pub struct PcrEvent<'b> {
+    pub pcr_handle: AuthHandle<'b>,
+    pub event_data: &'b [u8],
+}
+impl CommandData for PcrEvent<'_> {
+    marshal_fields!(handles: PcrEvent { pcr_handle.handle });
+    marshal_fields!(params: PcrEvent { event_data });
+}
+impl Command for PcrEvent<'_> {
+    const CODE: tpm::CC = tpm::CC::PcrEvent;
+    type Response<'t> = PcrEventResponse<'t>;
+}
+impl Auths<1> for PcrEvent<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.pcr_handle.auth]
+    }
+}
+
+/// TPM2_PCR_Event Response
+///
+/// See [PcrEvent] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PcrEventResponse<'t> {
+    pub digests: tpml::DigestValuesOut<'t>,
+}
+impl<'t> ResponseData<'t> for PcrEventResponse<'t> {
+    unmarshal_fields!('t, params: PcrEventResponse { digests });
+}
 
 /// TPM2_PCR_Read Command
 ///
@@ -868,9 +982,7 @@ pub struct PcrRead<'b> {
     pub pcr_selection: tpml::PcrSelectionIn<'b>,
 }
 impl CommandData for PcrRead<'_> {
-    fn marshal_params(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.pcr_selection.marshal(buf)
-    }
+    marshal_fields!(params: PcrRead { pcr_selection });
 }
 impl Command for PcrRead<'_> {
     const CODE: tpm::CC = tpm::CC::PcrRead;
@@ -888,76 +1000,122 @@ pub struct PcrReadResponse<'t> {
     pub pcr_values: tpml::DigestOut<'t>,
 }
 impl<'t> ResponseData<'t> for PcrReadResponse<'t> {
-    fn unmarshal_params(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
-        self.pcr_update_counter.unmarshal(buf)?;
-        self.pcr_selection.unmarshal(buf)?;
-        self.pcr_values.unmarshal(buf)
+    unmarshal_fields!('t, params: PcrReadResponse { pcr_update_counter, pcr_selection, pcr_values });
+}
+
+/// TPM2_PCR_Allocate Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 22.5
+///
+/// The new allocation takes effect after the next TPM Reset. The
+/// `auth_handle` must be [`tpm::rh::PLATFORM`]. See
+/// [`TpmExt::pcr_allocate`](crate::TpmExt::pcr_allocate) for a higher-level
+/// interface.
+#[derive(Clone, Copy, Debug)]
+pub struct PcrAllocate<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub pcr_allocation: tpml::PcrSelectionIn<'b>,
+}
+impl CommandData for PcrAllocate<'_> {
+    marshal_fields!(handles: PcrAllocate { auth_handle.handle });
+    marshal_fields!(params: PcrAllocate { pcr_allocation });
+}
+impl Command for PcrAllocate<'_> {
+    const CODE: tpm::CC = tpm::CC::PcrAllocate;
+    type Response<'t> = PcrAllocateResponse;
+}
+impl Auths<1> for PcrAllocate<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
     }
 }
 
-// /// TPM2_PCR_Allocate Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 22.5
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct PcrAllocate {
-//     pub todo: (),
-// }
-// /// TPM2_PCR_Allocate Response
-// ///
-// /// See [PcrAllocate] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct PcrAllocateResponse {
-//     pub todo: (),
-// }
+/// TPM2_PCR_Allocate Response
+///
+/// See [PcrAllocate] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PcrAllocateResponse {
+    pub allocation_success: bool,
+    pub max_pcr: u32,
+    pub size_needed: u32,
+    pub size_available: u32,
+}
+impl ResponseData<'_> for PcrAllocateResponse {
+    unmarshal_fields!('_, params: PcrAllocateResponse { allocation_success, max_pcr, size_needed, size_available });
+}
 
-// /// TPM2_PCR_SetAuthPolicy Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 22.6
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct PcrSetAuthPolicy {
-//     pub todo: (),
-// }
-// /// TPM2_PCR_SetAuthPolicy Response
-// ///
-// /// See [PcrSetAuthPolicy] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct PcrSetAuthPolicyResponse {
-//     pub todo: (),
-// }
+/// TPM2_PCR_SetAuthPolicy Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 22.6
+///
+/// The `auth_handle` must be [`tpm::rh::PLATFORM`]. Note that the PCR is a
+/// parameter (not a handle) of this command.
+#[derive(Clone, Copy, Debug)]
+pub struct PcrSetAuthPolicy<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub auth_policy: &'b [u8],
+    pub hash_alg: Option<tpmi::AlgHash>,
+    pub pcr_num: Handle,
+}
+impl CommandData for PcrSetAuthPolicy<'_> {
+    marshal_fields!(handles: PcrSetAuthPolicy { auth_handle.handle });
+    marshal_fields!(params: PcrSetAuthPolicy { auth_policy, hash_alg, pcr_num });
+}
+impl Command for PcrSetAuthPolicy<'_> {
+    const CODE: tpm::CC = tpm::CC::PcrSetAuthPolicy;
+    type Response<'t> = ();
+}
+impl Auths<1> for PcrSetAuthPolicy<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_PCR_SetAuthValue Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 22.7
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct PcrSetAuthValue {
-//     pub todo: (),
-// }
-// /// TPM2_PCR_SetAuthValue Response
-// ///
-// /// See [PcrSetAuthValue] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct PcrSetAuthValueResponse {
-//     pub todo: (),
-// }
+/// TPM2_PCR_SetAuthValue Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 22.7
+#[derive(Clone, Copy, Debug)]
+pub struct PcrSetAuthValue<'b> {
+    pub pcr_handle: AuthHandle<'b>,
+    pub auth: &'b [u8],
+}
+impl CommandData for PcrSetAuthValue<'_> {
+    marshal_fields!(handles: PcrSetAuthValue { pcr_handle.handle });
+    marshal_fields!(params: PcrSetAuthValue { auth });
+}
+impl Command for PcrSetAuthValue<'_> {
+    const CODE: tpm::CC = tpm::CC::PcrSetAuthValue;
+    type Response<'t> = ();
+}
+impl Auths<1> for PcrSetAuthValue<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.pcr_handle.auth]
+    }
+}
 
-// /// TPM2_PCR_Reset Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 22.8
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct PcrReset {
-//     pub todo: (),
-// }
-// /// TPM2_PCR_Reset Response
-// ///
-// /// See [PcrReset] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct PcrResetResponse {
-//     pub todo: (),
-// }
+/// TPM2_PCR_Reset Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 22.8
+#[derive(Clone, Copy, Debug)]
+pub struct PcrReset<'b> {
+    pub pcr_handle: AuthHandle<'b>,
+}
+impl CommandData for PcrReset<'_> {
+    marshal_fields!(handles: PcrReset { pcr_handle.handle });
+}
+impl Command for PcrReset<'_> {
+    const CODE: tpm::CC = tpm::CC::PcrReset;
+    type Response<'t> = ();
+}
+impl Auths<1> for PcrReset<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.pcr_handle.auth]
+    }
+}
 
 // /// TPM2_PolicySigned Command
 // ///
@@ -1303,149 +1461,233 @@ pub struct CreatePrimaryResponse<'t> {
     pub name: tpm2b::Name,
 }
 
-// /// TPM2_HierarchyControl Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 24.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct HierarchyControl {
-//     pub todo: (),
-// }
-// /// TPM2_HierarchyControl Response
-// ///
-// /// See [HierarchyControl] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct HierarchyControlResponse {
-//     pub todo: (),
-// }
-
-// /// TPM2_SetPrimaryPolicy Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 24.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct SetPrimaryPolicy {
-//     pub todo: (),
-// }
-// /// TPM2_SetPrimaryPolicy Response
-// ///
-// /// See [SetPrimaryPolicy] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct SetPrimaryPolicyResponse {
-//     pub todo: (),
-// }
+/// TPM2_HierarchyControl Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 24.2
+///
+/// Enables or disables the hierarchy `enable` (one of [`tpm::rh::OWNER`],
+/// [`tpm::rh::ENDORSEMENT`], [`tpm::rh::PLATFORM`] or [`tpm::rh::PLATFORM_NV`]).
+/// The `auth_handle` must be [`tpm::rh::OWNER`] or [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct HierarchyControl<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub enable: tpmi::RhEnables,
+    pub state: bool,
+}
+impl CommandData for HierarchyControl<'_> {
+    marshal_fields!(handles: HierarchyControl { auth_handle.handle });
+    marshal_fields!(params: HierarchyControl { enable, state });
+}
+impl Command for HierarchyControl<'_> {
+    const CODE: tpm::CC = tpm::CC::HierarchyControl;
+    type Response<'t> = ();
+}
+impl Auths<1> for HierarchyControl<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_ChangePPS Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 24.4
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct ChangePps {
-//     pub todo: (),
-// }
-// /// TPM2_ChangePPS Response
-// ///
-// /// See [ChangePps] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ChangePpsResponse {
-//     pub todo: (),
-// }
+/// TPM2_SetPrimaryPolicy Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 24.3
+///
+/// Sets the authorization policy of the hierarchy `auth_handle`.
+#[derive(Clone, Copy, Debug)]
+pub struct SetPrimaryPolicy<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub auth_policy: &'b [u8],
+    pub hash_alg: Option<tpmi::AlgHash>,
+}
+impl CommandData for SetPrimaryPolicy<'_> {
+    marshal_fields!(handles: SetPrimaryPolicy { auth_handle.handle });
+    marshal_fields!(params: SetPrimaryPolicy { auth_policy, hash_alg });
+}
+impl Command for SetPrimaryPolicy<'_> {
+    const CODE: tpm::CC = tpm::CC::SetPrimaryPolicy;
+    type Response<'t> = ();
+}
+impl Auths<1> for SetPrimaryPolicy<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_ChangeEPS Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 24.5
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct ChangeEps {
-//     pub todo: (),
-// }
-// /// TPM2_ChangeEPS Response
-// ///
-// /// See [ChangeEps] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ChangeEpsResponse {
-//     pub todo: (),
-// }
+/// TPM2_ChangePPS Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 24.4
+///
+/// The `auth_handle` must be [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct ChangePps<'b> {
+    pub auth_handle: AuthHandle<'b>,
+}
+impl CommandData for ChangePps<'_> {
+    marshal_fields!(handles: ChangePps { auth_handle.handle });
+}
+impl Command for ChangePps<'_> {
+    const CODE: tpm::CC = tpm::CC::ChangePps;
+    type Response<'t> = ();
+}
+impl Auths<1> for ChangePps<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_Clear Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 24.6
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct Clear {
-//     pub todo: (),
-// }
-// /// TPM2_Clear Response
-// ///
-// /// See [Clear] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ClearResponse {
-//     pub todo: (),
-// }
+/// TPM2_ChangeEPS Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 24.5
+///
+/// The `auth_handle` must be [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct ChangeEps<'b> {
+    pub auth_handle: AuthHandle<'b>,
+}
+impl CommandData for ChangeEps<'_> {
+    marshal_fields!(handles: ChangeEps { auth_handle.handle });
+}
+impl Command for ChangeEps<'_> {
+    const CODE: tpm::CC = tpm::CC::ChangeEps;
+    type Response<'t> = ();
+}
+impl Auths<1> for ChangeEps<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_ClearControl Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 24.7
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct ClearControl {
-//     pub todo: (),
-// }
-// /// TPM2_ClearControl Response
-// ///
-// /// See [ClearControl] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ClearControlResponse {
-//     pub todo: (),
-// }
+/// TPM2_Clear Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 24.6
+///
+/// The `auth_handle` must be [`tpm::rh::LOCKOUT`] or [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct Clear<'b> {
+    pub auth_handle: AuthHandle<'b>,
+}
+impl CommandData for Clear<'_> {
+    marshal_fields!(handles: Clear { auth_handle.handle });
+}
+impl Command for Clear<'_> {
+    const CODE: tpm::CC = tpm::CC::Clear;
+    type Response<'t> = ();
+}
+impl Auths<1> for Clear<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_HierarchyChangeAuth Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 24.8
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct HierarchyChangeAuth {
-//     pub todo: (),
-// }
-// /// TPM2_HierarchyChangeAuth Response
-// ///
-// /// See [HierarchyChangeAuth] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct HierarchyChangeAuthResponse {
-//     pub todo: (),
-// }
+/// TPM2_ClearControl Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 24.7
+///
+/// The `auth_handle` must be [`tpm::rh::LOCKOUT`] or [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct ClearControl<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub disable: bool,
+}
+impl CommandData for ClearControl<'_> {
+    marshal_fields!(handles: ClearControl { auth_handle.handle });
+    marshal_fields!(params: ClearControl { disable });
+}
+impl Command for ClearControl<'_> {
+    const CODE: tpm::CC = tpm::CC::ClearControl;
+    type Response<'t> = ();
+}
+impl Auths<1> for ClearControl<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_DictionaryAttackLockReset Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 25.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct DictionaryAttackLockReset {
-//     pub todo: (),
-// }
-// /// TPM2_DictionaryAttackLockReset Response
-// ///
-// /// See [DictionaryAttackLockReset] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct DictionaryAttackLockResetResponse {
-//     pub todo: (),
-// }
+/// TPM2_HierarchyChangeAuth Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 24.8
+///
+/// The `auth_handle` is one of [`tpm::rh::LOCKOUT`], [`tpm::rh::ENDORSEMENT`],
+/// [`tpm::rh::OWNER`] or [`tpm::rh::PLATFORM`]. See
+/// [`TpmExt::take_ownership`](crate::TpmExt::take_ownership) for setting the
+/// authorization values of multiple hierarchies.
+#[derive(Clone, Copy, Debug)]
+pub struct HierarchyChangeAuth<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub new_auth: &'b [u8],
+}
+impl CommandData for HierarchyChangeAuth<'_> {
+    marshal_fields!(handles: HierarchyChangeAuth { auth_handle.handle });
+    marshal_fields!(params: HierarchyChangeAuth { new_auth });
+}
+impl Command for HierarchyChangeAuth<'_> {
+    const CODE: tpm::CC = tpm::CC::HierarchyChangeAuth;
+    type Response<'t> = ();
+}
+impl Auths<1> for HierarchyChangeAuth<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_DictionaryAttackParameters Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 25.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct DictionaryAttackParameters {
-//     pub todo: (),
-// }
-// /// TPM2_DictionaryAttackParameters Response
-// ///
-// /// See [DictionaryAttackParameters] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct DictionaryAttackParametersResponse {
-//     pub todo: (),
-// }
+/// TPM2_DictionaryAttackLockReset Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 25.2
+///
+/// Resets the failed authorization counter. The `lock_handle` must be
+/// [`tpm::rh::LOCKOUT`].
+#[derive(Clone, Copy, Debug)]
+pub struct DictionaryAttackLockReset<'b> {
+    pub lock_handle: AuthHandle<'b>,
+}
+impl CommandData for DictionaryAttackLockReset<'_> {
+    marshal_fields!(handles: DictionaryAttackLockReset { lock_handle.handle });
+}
+impl Command for DictionaryAttackLockReset<'_> {
+    const CODE: tpm::CC = tpm::CC::DictionaryAttackLockReset;
+    type Response<'t> = ();
+}
+impl Auths<1> for DictionaryAttackLockReset<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.lock_handle.auth]
+    }
+}
+
+/// TPM2_DictionaryAttackParameters Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 25.3
+///
+/// Changes the dictionary attack lockout parameters. The times are in
+/// seconds. The `lock_handle` must be [`tpm::rh::LOCKOUT`].
+#[derive(Clone, Copy, Debug)]
+pub struct DictionaryAttackParameters<'b> {
+    pub lock_handle: AuthHandle<'b>,
+    pub new_max_tries: u32,
+    pub new_recovery_time: u32,
+    pub lockout_recovery: u32,
+}
+impl CommandData for DictionaryAttackParameters<'_> {
+    marshal_fields!(handles: DictionaryAttackParameters { lock_handle.handle });
+    marshal_fields!(params: DictionaryAttackParameters { new_max_tries, new_recovery_time, lockout_recovery });
+}
+impl Command for DictionaryAttackParameters<'_> {
+    const CODE: tpm::CC = tpm::CC::DictionaryAttackParameters;
+    type Response<'t> = ();
+}
+impl Auths<1> for DictionaryAttackParameters<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.lock_handle.auth]
+    }
+}
 
 // /// TPM2_PP_Commands Command
 // ///
@@ -1527,53 +1769,89 @@ pub struct CreatePrimaryResponse<'t> {
 //     pub todo: (),
 // }
 
-// /// TPM2_ContextSave Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 28.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct ContextSave {
-//     pub todo: (),
-// }
-// /// TPM2_ContextSave Response
-// ///
-// /// See [ContextSave] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ContextSaveResponse {
-//     pub todo: (),
-// }
+/// TPM2_ContextSave Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 28.2
+///
+/// Saves the context of the transient object or session `save_handle`. A
+/// saved object remains loaded (and must still be flushed with
+/// [`FlushContext`]), while a saved session no longer occupies TPM memory.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ContextSave {
+    pub save_handle: tpmi::DhContext,
+}
+impl CommandData for ContextSave {
+    marshal_fields!(handles: ContextSave { save_handle });
+}
+impl Command for ContextSave {
+    const CODE: tpm::CC = tpm::CC::ContextSave;
+    type Response<'t> = ContextSaveResponse<'t>;
+}
+impl Auths<0> for ContextSave {}
 
-// /// TPM2_ContextLoad Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 28.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct ContextLoad {
-//     pub todo: (),
-// }
-// /// TPM2_ContextLoad Response
-// ///
-// /// See [ContextLoad] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ContextLoadResponse {
-//     pub todo: (),
-// }
+/// TPM2_ContextSave Response
+///
+/// See [ContextSave] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ContextSaveResponse<'t> {
+    pub context: tpms::Context<'t>,
+}
+impl<'t> ResponseData<'t> for ContextSaveResponse<'t> {
+    unmarshal_fields!('t, params: ContextSaveResponse { context });
+}
 
-// /// TPM2_FlushContext Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 28.4
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct FlushContext {
-//     pub todo: (),
-// }
-// /// TPM2_FlushContext Response
-// ///
-// /// See [FlushContext] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct FlushContextResponse {
-//     pub todo: (),
-// }
+/// TPM2_ContextLoad Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 28.3
+///
+/// Loads a context saved with [`ContextSave`]. A session is loaded with the
+/// same handle it was saved with, while an object is given a new handle.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ContextLoad<'b> {
+    pub context: tpms::Context<'b>,
+}
+impl CommandData for ContextLoad<'_> {
+    marshal_fields!(params: ContextLoad { context });
+}
+impl Command for ContextLoad<'_> {
+    const CODE: tpm::CC = tpm::CC::ContextLoad;
+    type Response<'t> = ContextLoadResponse;
+}
+impl Auths<0> for ContextLoad<'_> {}
+
+/// TPM2_ContextLoad Response
+///
+/// See [ContextLoad] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ContextLoadResponse {
+    pub loaded_handle: tpmi::DhSaved,
+}
+impl ResponseData<'_> for ContextLoadResponse {
+    unmarshal_fields!('_, handles: ContextLoadResponse { loaded_handle });
+}
+
+/// TPM2_FlushContext Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 28.4
+///
+/// Removes the transient object or session `flush_handle` from the TPM. A
+/// session can be flushed whether it is loaded or saved. Note that the handle
+/// is a parameter of this command, not a handle.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FlushContext {
+    pub flush_handle: tpmi::DhContext,
+}
+impl CommandData for FlushContext {
+    marshal_fields!(params: FlushContext { flush_handle });
+}
+impl Command for FlushContext {
+    const CODE: tpm::CC = tpm::CC::FlushContext;
+    type Response<'t> = ();
+}
+impl Auths<0> for FlushContext {}
 
 // /// TPM2_EvictControl Command
 // ///
@@ -1613,282 +1891,455 @@ pub struct ReadClockResponse {
     pub current_time: tpms::TimeInfo,
 }
 impl ResponseData<'_> for ReadClockResponse {
-    fn unmarshal_params(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
-        self.current_time.unmarshal(buf)
+    unmarshal_fields!('_, params: ReadClockResponse { current_time });
+}
+
+/// TPM2_ClockSet Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 29.2
+///
+/// Advances Clock to `new_time` (in milliseconds). Clock can never be set to
+/// an earlier value. The `auth` handle must be [`tpm::rh::OWNER`] or
+/// [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct ClockSet<'b> {
+    pub auth: AuthHandle<'b>,
+    pub new_time: u64,
+}
+impl CommandData for ClockSet<'_> {
+    marshal_fields!(handles: ClockSet { auth.handle });
+    marshal_fields!(params: ClockSet { new_time });
+}
+impl Command for ClockSet<'_> {
+    const CODE: tpm::CC = tpm::CC::ClockSet;
+    type Response<'t> = ();
+}
+impl Auths<1> for ClockSet<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth.auth]
     }
 }
 
-// /// TPM2_ClockSet Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 29.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct ClockSet {
-//     pub todo: (),
-// }
-// /// TPM2_ClockSet Response
-// ///
-// /// See [ClockSet] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ClockSetResponse {
-//     pub todo: (),
-// }
+/// TPM2_ClockRateAdjust Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 29.3
+///
+/// The `auth` handle must be [`tpm::rh::OWNER`] or [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct ClockRateAdjust<'b> {
+    pub auth: AuthHandle<'b>,
+    pub rate_adjust: tpm::ClockAdjust,
+}
+impl CommandData for ClockRateAdjust<'_> {
+    marshal_fields!(handles: ClockRateAdjust { auth.handle });
+    marshal_fields!(params: ClockRateAdjust { rate_adjust });
+}
+impl Command for ClockRateAdjust<'_> {
+    const CODE: tpm::CC = tpm::CC::ClockRateAdjust;
+    type Response<'t> = ();
+}
+impl Auths<1> for ClockRateAdjust<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth.auth]
+    }
+}
 
-// /// TPM2_ClockRateAdjust Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 29.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct ClockRateAdjust {
-//     pub todo: (),
-// }
-// /// TPM2_ClockRateAdjust Response
-// ///
-// /// See [ClockRateAdjust] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct ClockRateAdjustResponse {
-//     pub todo: (),
-// }
+/// TPM2_GetCapability Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 30.2
+#[derive(Clone, Copy, Default, Debug)]
+pub struct GetCapability {
+    pub capability: tpm::Cap,
+    pub property: u32,
+    pub property_count: u32,
+}
+impl CommandData for GetCapability {
+    marshal_fields!(params: GetCapability { capability, property, property_count });
+}
+impl Command for GetCapability {
+    const CODE: tpm::CC = tpm::CC::GetCapability;
+    type Response<'t> = GetCapabilityResponse<'t>;
+}
+impl Auths<0> for GetCapability {}
 
-// /// TPM2_GetCapability Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 30.2
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct GetCapability {
-//     pub todo: (),
-// }
-// /// TPM2_GetCapability Response
-// ///
-// /// See [GetCapability] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct GetCapabilityResponse {
-//     pub todo: (),
-// }
+/// TPM2_GetCapability Response
+///
+/// See [GetCapability] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct GetCapabilityResponse<'t> {
+    pub more_data: bool,
+    pub capability_data: tpms::CapabilityData<'t>,
+}
+impl<'t> ResponseData<'t> for GetCapabilityResponse<'t> {
+    unmarshal_fields!('t, params: GetCapabilityResponse { more_data, capability_data });
+}
 
-// /// TPM2_TestParms Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 30.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct TestParms {
-//     pub todo: (),
-// }
-// /// TPM2_TestParms Response
-// ///
-// /// See [TestParms] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct TestParmsResponse {
-//     pub todo: (),
-// }
+/// TPM2_TestParms Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 30.3
+///
+/// See [`TpmExt::supports`](crate::TpmExt::supports) for a higher-level
+/// interface.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TestParms {
+    pub parameters: tpmt::PublicParms,
+}
+impl CommandData for TestParms {
+    marshal_fields!(params: TestParms { parameters });
+}
+impl Command for TestParms {
+    const CODE: tpm::CC = tpm::CC::TestParms;
+    type Response<'t> = ();
+}
+impl Auths<0> for TestParms {}
 
-// /// TPM2_NV_DefineSpace Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.3
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvDefineSpace {
-//     pub todo: (),
-// }
-// /// TPM2_NV_DefineSpace Response
-// ///
-// /// See [NvDefineSpace] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvDefineSpaceResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_DefineSpace Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.3
+///
+/// The `auth_handle` must be [`tpm::rh::OWNER`] or [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct NvDefineSpace<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub auth: &'b [u8],
+    pub public_info: &'b tpm2b::NvPublicIn<'b>,
+}
+impl CommandData for NvDefineSpace<'_> {
+    marshal_fields!(handles: NvDefineSpace { auth_handle.handle });
+    marshal_fields!(params: NvDefineSpace { auth, public_info });
+}
+impl Command for NvDefineSpace<'_> {
+    const CODE: tpm::CC = tpm::CC::NvDefineSpace;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvDefineSpace<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_UndefineSpace Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.4
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvUndefineSpace {
-//     pub todo: (),
-// }
-// /// TPM2_NV_UndefineSpace Response
-// ///
-// /// See [NvUndefineSpace] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvUndefineSpaceResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_UndefineSpace Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.4
+///
+/// The `auth_handle` must be [`tpm::rh::OWNER`] or [`tpm::rh::PLATFORM`].
+/// Indices with [`tpma::Nv::POLICY_DELETE`](crate::types::tpma::Nv::POLICY_DELETE)
+/// set must be deleted with [`NvUndefineSpaceSpecial`].
+#[derive(Clone, Copy, Debug)]
+pub struct NvUndefineSpace<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+}
+impl CommandData for NvUndefineSpace<'_> {
+    marshal_fields!(handles: NvUndefineSpace { auth_handle.handle, nv_index });
+}
+impl Command for NvUndefineSpace<'_> {
+    const CODE: tpm::CC = tpm::CC::NvUndefineSpace;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvUndefineSpace<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_UndefineSpaceSpecial Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.5
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvUndefineSpaceSpecial {
-//     pub todo: (),
-// }
-// /// TPM2_NV_UndefineSpaceSpecial Response
-// ///
-// /// See [NvUndefineSpaceSpecial] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvUndefineSpaceSpecialResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_UndefineSpaceSpecial Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.5
+///
+/// The `nv_index` must be authorized with a policy session, and the
+/// `platform` handle must be [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct NvUndefineSpaceSpecial<'b> {
+    pub nv_index: AuthHandle<'b>,
+    pub platform: AuthHandle<'b>,
+}
+impl CommandData for NvUndefineSpaceSpecial<'_> {
+    marshal_fields!(handles: NvUndefineSpaceSpecial { nv_index.handle, platform.handle });
+}
+impl Command for NvUndefineSpaceSpecial<'_> {
+    const CODE: tpm::CC = tpm::CC::NvUndefineSpaceSpecial;
+    type Response<'t> = ();
+}
+impl Auths<2> for NvUndefineSpaceSpecial<'_> {
+    fn auths(&self) -> [&dyn Auth; 2] {
+        [self.nv_index.auth, self.platform.auth]
+    }
+}
 
-// /// TPM2_NV_ReadPublic Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.6
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvReadPublic {
-//     pub todo: (),
-// }
-// /// TPM2_NV_ReadPublic Response
-// ///
-// /// See [NvReadPublic] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvReadPublicResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_ReadPublic Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.6
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NvReadPublic {
+    pub nv_index: tpmi::RhNvIndex,
+}
+impl CommandData for NvReadPublic {
+    marshal_fields!(handles: NvReadPublic { nv_index });
+}
+impl Command for NvReadPublic {
+    const CODE: tpm::CC = tpm::CC::NvReadPublic;
+    type Response<'t> = NvReadPublicResponse<'t>;
+}
+impl Auths<0> for NvReadPublic {}
 
-// /// TPM2_NV_Write Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.7
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvWrite {
-//     pub todo: (),
-// }
-// /// TPM2_NV_Write Response
-// ///
-// /// See [NvWrite] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvWriteResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_ReadPublic Response
+///
+/// See [NvReadPublic] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NvReadPublicResponse<'t> {
+    pub nv_public: tpm2b::NvPublicOut<'t>,
+    pub nv_name: tpm2b::Name,
+}
+impl<'t> ResponseData<'t> for NvReadPublicResponse<'t> {
+    unmarshal_fields!('t, params: NvReadPublicResponse { nv_public, nv_name });
+}
 
-// /// TPM2_NV_Increment Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.8
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvIncrement {
-//     pub todo: (),
-// }
-// /// TPM2_NV_Increment Response
-// ///
-// /// See [NvIncrement] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvIncrementResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_Write Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.7
+///
+/// The `auth_handle` is either the `nv_index` itself, [`tpm::rh::OWNER`], or
+/// [`tpm::rh::PLATFORM`]. See [`TpmExt::nv_write_all`](crate::TpmExt::nv_write_all)
+/// for writing more than a single buffer of data.
+#[derive(Clone, Copy, Debug)]
+pub struct NvWrite<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+    pub data: &'b [u8],
+    pub offset: u16,
+}
+impl CommandData for NvWrite<'_> {
+    marshal_fields!(handles: NvWrite { auth_handle.handle, nv_index });
+    marshal_fields!(params: NvWrite { data, offset });
+}
+impl Command for NvWrite<'_> {
+    const CODE: tpm::CC = tpm::CC::NvWrite;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvWrite<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_Extend Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.9
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvExtend {
-//     pub todo: (),
-// }
-// /// TPM2_NV_Extend Response
-// ///
-// /// See [NvExtend] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvExtendResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_Increment Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.8
+///
+/// See [`NvCounter`](crate::nv::NvCounter) for a higher-level interface.
+#[derive(Clone, Copy, Debug)]
+pub struct NvIncrement<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+}
+impl CommandData for NvIncrement<'_> {
+    marshal_fields!(handles: NvIncrement { auth_handle.handle, nv_index });
+}
+impl Command for NvIncrement<'_> {
+    const CODE: tpm::CC = tpm::CC::NvIncrement;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvIncrement<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_SetBits Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.10
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvSetBits {
-//     pub todo: (),
-// }
-// /// TPM2_NV_SetBits Response
-// ///
-// /// See [NvSetBits] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvSetBitsResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_Extend Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.9
+///
+/// See [`NvExtendIndex`](crate::nv::NvExtendIndex) for a higher-level
+/// interface.
+#[derive(Clone, Copy, Debug)]
+pub struct NvExtend<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+    pub data: &'b [u8],
+}
+impl CommandData for NvExtend<'_> {
+    marshal_fields!(handles: NvExtend { auth_handle.handle, nv_index });
+    marshal_fields!(params: NvExtend { data });
+}
+impl Command for NvExtend<'_> {
+    const CODE: tpm::CC = tpm::CC::NvExtend;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvExtend<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_WriteLock Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.11
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvWriteLock {
-//     pub todo: (),
-// }
-// /// TPM2_NV_WriteLock Response
-// ///
-// /// See [NvWriteLock] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvWriteLockResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_SetBits Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.10
+///
+/// See [`NvBitField`](crate::nv::NvBitField) for a higher-level interface.
+#[derive(Clone, Copy, Debug)]
+pub struct NvSetBits<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+    pub bits: u64,
+}
+impl CommandData for NvSetBits<'_> {
+    marshal_fields!(handles: NvSetBits { auth_handle.handle, nv_index });
+    marshal_fields!(params: NvSetBits { bits });
+}
+impl Command for NvSetBits<'_> {
+    const CODE: tpm::CC = tpm::CC::NvSetBits;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvSetBits<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_GlobalWriteLock Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.12
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvGlobalWriteLock {
-//     pub todo: (),
-// }
-// /// TPM2_NV_GlobalWriteLock Response
-// ///
-// /// See [NvGlobalWriteLock] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvGlobalWriteLockResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_WriteLock Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.11
+#[derive(Clone, Copy, Debug)]
+pub struct NvWriteLock<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+}
+impl CommandData for NvWriteLock<'_> {
+    marshal_fields!(handles: NvWriteLock { auth_handle.handle, nv_index });
+}
+impl Command for NvWriteLock<'_> {
+    const CODE: tpm::CC = tpm::CC::NvWriteLock;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvWriteLock<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_Read Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.13
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvRead {
-//     pub todo: (),
-// }
-// /// TPM2_NV_Read Response
-// ///
-// /// See [NvRead] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvReadResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_GlobalWriteLock Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.12
+///
+/// The `auth_handle` must be [`tpm::rh::OWNER`] or [`tpm::rh::PLATFORM`].
+#[derive(Clone, Copy, Debug)]
+pub struct NvGlobalWriteLock<'b> {
+    pub auth_handle: AuthHandle<'b>,
+}
+impl CommandData for NvGlobalWriteLock<'_> {
+    marshal_fields!(handles: NvGlobalWriteLock { auth_handle.handle });
+}
+impl Command for NvGlobalWriteLock<'_> {
+    const CODE: tpm::CC = tpm::CC::NvGlobalWriteLock;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvGlobalWriteLock<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_ReadLock Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.14
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvReadLock {
-//     pub todo: (),
-// }
-// /// TPM2_NV_ReadLock Response
-// ///
-// /// See [NvReadLock] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvReadLockResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_Read Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.13
+///
+/// The `auth_handle` is either the `nv_index` itself, [`tpm::rh::OWNER`], or
+/// [`tpm::rh::PLATFORM`]. See [`TpmExt::nv_read_all`](crate::TpmExt::nv_read_all)
+/// for reading an entire NV Index.
+#[derive(Clone, Copy, Debug)]
+pub struct NvRead<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+    pub size: u16,
+    pub offset: u16,
+}
+impl CommandData for NvRead<'_> {
+    marshal_fields!(handles: NvRead { auth_handle.handle, nv_index });
+    marshal_fields!(params: NvRead { size, offset });
+}
+impl Command for NvRead<'_> {
+    const CODE: tpm::CC = tpm::CC::NvRead;
+    type Response<'t> = NvReadResponse<'t>;
+}
+impl Auths<1> for NvRead<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
 
-// /// TPM2_NV_ChangeAuth Command
-// ///
-// /// This command (and its response) are defined in the
-// /// TPM2 Library Specification - v1.59 - Part 3 - Section 31.15
-// #[derive(Clone, Copy, Default, Debug, CommandData, Command, Auths)]
-// pub struct NvChangeAuth {
-//     pub todo: (),
-// }
-// /// TPM2_NV_ChangeAuth Response
-// ///
-// /// See [NvChangeAuth] for more information.
-// #[derive(Clone, Copy, Default, Debug, ResponseData)]
-// pub struct NvChangeAuthResponse {
-//     pub todo: (),
-// }
+/// TPM2_NV_Read Response
+///
+/// See [NvRead] for more information.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NvReadResponse<'t> {
+    pub data: &'t [u8],
+}
+impl<'t> ResponseData<'t> for NvReadResponse<'t> {
+    unmarshal_fields!('t, params: NvReadResponse { data });
+}
+
+/// TPM2_NV_ReadLock Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.14
+#[derive(Clone, Copy, Debug)]
+pub struct NvReadLock<'b> {
+    pub auth_handle: AuthHandle<'b>,
+    pub nv_index: tpmi::RhNvIndex,
+}
+impl CommandData for NvReadLock<'_> {
+    marshal_fields!(handles: NvReadLock { auth_handle.handle, nv_index });
+}
+impl Command for NvReadLock<'_> {
+    const CODE: tpm::CC = tpm::CC::NvReadLock;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvReadLock<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.auth_handle.auth]
+    }
+}
+
+/// TPM2_NV_ChangeAuth Command
+///
+/// This command (and its response) are defined in the
+/// TPM2 Library Specification - v1.59 - Part 3 - Section 31.15
+///
+/// The `nv_index` must be authorized with a policy session.
+#[derive(Clone, Copy, Debug)]
+pub struct NvChangeAuth<'b> {
+    pub nv_index: AuthHandle<'b>,
+    pub new_auth: &'b [u8],
+}
+impl CommandData for NvChangeAuth<'_> {
+    marshal_fields!(handles: NvChangeAuth { nv_index.handle });
+    marshal_fields!(params: NvChangeAuth { new_auth });
+}
+impl Command for NvChangeAuth<'_> {
+    const CODE: tpm::CC = tpm::CC::NvChangeAuth;
+    type Response<'t> = ();
+}
+impl Auths<1> for NvChangeAuth<'_> {
+    fn auths(&self) -> [&dyn Auth; 1] {
+        [self.nv_index.auth]
+    }
+}
 
 // /// TPM2_NV_Certify Command
 // ///
@@ -1969,3 +2420,146 @@ impl ResponseData<'_> for ReadClockResponse {
 // pub struct ActSetTimeoutResponse {
 //     pub todo: (),
 // }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use core::fmt::Debug;
+
+    use super::*;
+
+    /// Deterministic xorshift generator producing bytes biased towards small
+    /// values, so that sizes and counts in the input are often plausible.
+    pub(crate) struct Fuzzer(u64);
+
+    impl Fuzzer {
+        pub(crate) fn new() -> Self {
+            Self(0x2545_F491_4F6C_DD1D)
+        }
+
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        pub(crate) fn fill<'a>(&mut self, buf: &'a mut [u8]) -> &'a mut [u8] {
+            let len = self.next() as usize % (buf.len() + 1);
+            for b in &mut buf[..len] {
+                let r = self.next();
+                *b = match r % 8 {
+                    0..=3 => 0,
+                    4..=6 => (r >> 8) as u8 % 8,
+                    _ => (r >> 8) as u8,
+                };
+            }
+            &mut buf[..len]
+        }
+    }
+
+    fn fuzz<'t, R: ResponseData<'t> + Default + Debug>(buf: &'t [u8]) -> Option<R> {
+        let mut rsp = R::default();
+        let mut buf = buf;
+        rsp.unmarshal_handles(&mut buf).ok()?;
+        rsp.unmarshal_params(&mut buf).ok()?;
+        let _ = std::format!("{rsp:?}");
+        Some(rsp)
+    }
+
+    #[test]
+    fn fuzz_responses() {
+        let mut fuzzer = Fuzzer::new();
+        let mut storage = [0u8; 256];
+        for _ in 0..20_000 {
+            let buf = &*fuzzer.fill(&mut storage);
+            fuzz::<IncrementalSelfTestResponse>(buf).map(|r| r.to_do_list.count());
+            fuzz::<GetTestResultResponse>(buf);
+            fuzz::<ReadPublicResponse>(buf);
+            fuzz::<GetRandomResponse>(buf);
+            fuzz::<CommitResponse>(buf);
+            fuzz::<EcEphemeralResponse>(buf);
+            fuzz::<SignResponse>(buf);
+            fuzz::<PcrEventResponse>(buf).map(|r| r.digests.count());
+            if let Some(r) = fuzz::<PcrReadResponse>(buf) {
+                r.pcr_selection.for_each(|s| drop(std::format!("{s:?}")));
+                r.pcr_values.count();
+            }
+            fuzz::<PcrAllocateResponse>(buf);
+            fuzz::<ReadClockResponse>(buf);
+            if let Some(r) = fuzz::<GetCapabilityResponse>(buf) {
+                use tpms::CapabilityData::*;
+                match r.capability_data {
+                    Algs(l) => l.count(),
+                    Handles(l) => l.count(),
+                    Commands(l) => l.count(),
+                    PpCommands(l) | AuditCommands(l) => l.count(),
+                    Pcrs(l) => l.count(),
+                    TpmProperties(l) => l.count(),
+                    PcrProperties(l) => l.count(),
+                    EccCurves(l) => l.count(),
+                    AuthPolicies(l) => l.count(),
+                    Act(l) => l.count(),
+                    VendorProperty(v) => v.len(),
+                };
+            }
+            fuzz::<ContextSaveResponse>(buf);
+            fuzz::<ContextLoadResponse>(buf);
+            fuzz::<NvReadPublicResponse>(buf);
+            fuzz::<NvReadResponse>(buf);
+        }
+    }
+
+    #[test]
+    fn auth_handles() {
+        fn check<C: Command + Auths<N>, const N: usize>() {
+            assert_eq!(C::CODE.auth_handles(), Some(N), "{:?}", C::CODE);
+        }
+        check::<Startup, _>();
+        check::<Shutdown, _>();
+        check::<SelfTest, _>();
+        check::<IncrementalSelfTest, _>();
+        check::<GetTestResult, _>();
+        check::<ReadPublic, _>();
+        check::<GetRandom, _>();
+        check::<Commit, _>();
+        check::<EcEphemeral, _>();
+        check::<Sign, _>();
+        check::<PcrExtend, _>();
+        check::<PcrEvent, _>();
+        check::<PcrRead, _>();
+        check::<PcrAllocate, _>();
+        check::<PcrSetAuthPolicy, _>();
+        check::<PcrSetAuthValue, _>();
+        check::<PcrReset, _>();
+        check::<HierarchyControl, _>();
+        check::<SetPrimaryPolicy, _>();
+        check::<ChangePps, _>();
+        check::<ChangeEps, _>();
+        check::<Clear, _>();
+        check::<ClearControl, _>();
+        check::<HierarchyChangeAuth, _>();
+        check::<DictionaryAttackLockReset, _>();
+        check::<DictionaryAttackParameters, _>();
+        check::<ContextSave, _>();
+        check::<ContextLoad, _>();
+        check::<FlushContext, _>();
+        check::<ReadClock, _>();
+        check::<ClockSet, _>();
+        check::<ClockRateAdjust, _>();
+        check::<GetCapability, _>();
+        check::<TestParms, _>();
+        check::<NvDefineSpace, _>();
+        check::<NvUndefineSpace, _>();
+        check::<NvUndefineSpaceSpecial, _>();
+        check::<NvReadPublic, _>();
+        check::<NvWrite, _>();
+        check::<NvIncrement, _>();
+        check::<NvExtend, _>();
+        check::<NvSetBits, _>();
+        check::<NvWriteLock, _>();
+        check::<NvGlobalWriteLock, _>();
+        check::<NvRead, _>();
+        check::<NvReadLock, _>();
+        check::<NvChangeAuth, _>();
+    }
+}
@@ -0,0 +1,432 @@
+//! Host-side helpers for ECDAA on the BN_P256 curve
+//!
+//! In an ECDAA signature, the TPM only performs the operations that involve
+//! the private key ([`Commit`] and [`Sign`]). The host is responsible for
+//! randomizing the issuer's [`Credential`] and for mapping the basename onto
+//! the curve. This module contains just enough BN_P256 group arithmetic to
+//! do that.
+//!
+//! This code is meant for prototyping. Nothing here runs in constant time, and
+//! the pairings needed to verify a signature are not implemented.
+//!
+//! [`Commit`]: crate::commands::Commit
+//! [`Sign`]: crate::commands::Sign
+
+use core::fmt;
+
+use crate::types::tpms;
+
+/// BN_P256 field modulus (little-endian 64-bit limbs)
+const P: [u64; 4] = [
+    0xD3292DDBAED33013,
+    0x0CDC65FB12980A82,
+    0x46E5F25EEE71A49F,
+    0xFFFFFFFFFFFCF0CD,
+];
+/// BN_P256 group order (little-endian 64-bit limbs)
+const N: [u64; 4] = [
+    0xF62D536CD10B500D,
+    0x0CDC65FB1299921A,
+    0x46E5F25EEE71A49E,
+    0xFFFFFFFFFFFCF0CD,
+];
+/// Size of a field element or scalar in bytes
+pub const SIZE: usize = 32;
+
+/// `-P^-1 mod 2^64`
+const P_INV: u64 = {
+    let mut inv = 1u64;
+    let mut i = 0;
+    while i < 6 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(P[0].wrapping_mul(inv)));
+        i += 1;
+    }
+    inv.wrapping_neg()
+};
+/// `2^512 mod P`, used to convert into Montgomery form
+const R2: [u64; 4] = {
+    // P > 2^255, so 2^256 mod P is just 2^256 - P.
+    let mut r = sub(&[0; 4], &P).0;
+    let mut i = 0;
+    while i < 256 {
+        r = add_mod(&r, &r, &P);
+        i += 1;
+    }
+    r
+};
+/// `P - 2`, used for inversion
+const P_MINUS_2: [u64; 4] = sub(&P, &[2, 0, 0, 0]).0;
+/// `(P + 1) / 4`, used for square roots (as `P = 3 mod 4`)
+const P_PLUS_1_DIV_4: [u64; 4] = {
+    let p1 = add(&P, &[1, 0, 0, 0]).0;
+    [
+        (p1[0] >> 2) | (p1[1] << 62),
+        (p1[1] >> 2) | (p1[2] << 62),
+        (p1[2] >> 2) | (p1[3] << 62),
+        p1[3] >> 2,
+    ]
+};
+
+const fn add(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut r = [0; 4];
+    let mut carry = false;
+    let mut i = 0;
+    while i < 4 {
+        let (s1, c1) = a[i].overflowing_add(b[i]);
+        let (s2, c2) = s1.overflowing_add(carry as u64);
+        r[i] = s2;
+        carry = c1 | c2;
+        i += 1;
+    }
+    (r, carry)
+}
+
+const fn sub(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut r = [0; 4];
+    let mut borrow = false;
+    let mut i = 0;
+    while i < 4 {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        r[i] = d2;
+        borrow = b1 | b2;
+        i += 1;
+    }
+    (r, borrow)
+}
+
+/// Computes `a + b mod m` for `a, b < m`
+const fn add_mod(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    let (s, carry) = add(a, b);
+    let (d, borrow) = sub(&s, m);
+    if carry || !borrow {
+        d
+    } else {
+        s
+    }
+}
+
+/// Computes `a - b mod m` for `a, b < m`
+const fn sub_mod(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    let (d, borrow) = sub(a, b);
+    if borrow {
+        add(&d, m).0
+    } else {
+        d
+    }
+}
+
+/// Montgomery multiplication: computes `a * b / 2^256 mod P`
+const fn mont_mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut t = [0u64; 6];
+    let mut i = 0;
+    while i < 4 {
+        let mut carry = 0u64;
+        let mut j = 0;
+        while j < 4 {
+            let v = t[j] as u128 + (a[j] as u128) * (b[i] as u128) + carry as u128;
+            t[j] = v as u64;
+            carry = (v >> 64) as u64;
+            j += 1;
+        }
+        let v = t[4] as u128 + carry as u128;
+        t[4] = v as u64;
+        t[5] = (v >> 64) as u64;
+
+        let m = t[0].wrapping_mul(P_INV);
+        let v = t[0] as u128 + (m as u128) * (P[0] as u128);
+        let mut carry = (v >> 64) as u64;
+        let mut j = 1;
+        while j < 4 {
+            let v = t[j] as u128 + (m as u128) * (P[j] as u128) + carry as u128;
+            t[j - 1] = v as u64;
+            carry = (v >> 64) as u64;
+            j += 1;
+        }
+        let v = t[4] as u128 + carry as u128;
+        t[3] = v as u64;
+        t[4] = t[5] + (v >> 64) as u64;
+        i += 1;
+    }
+    let r = [t[0], t[1], t[2], t[3]];
+    let (d, borrow) = sub(&r, &P);
+    if t[4] != 0 || !borrow {
+        d
+    } else {
+        r
+    }
+}
+
+/// Parses a big-endian integer of at most [`SIZE`] bytes
+fn limbs_from_be(b: &[u8]) -> Option<[u64; 4]> {
+    if b.len() > SIZE {
+        return None;
+    }
+    let mut arr = [0u8; SIZE];
+    arr[SIZE - b.len()..].copy_from_slice(b);
+    let mut r = [0; 4];
+    for (i, chunk) in arr.rchunks_exact(8).enumerate() {
+        r[i] = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    Some(r)
+}
+
+/// An element of the BN_P256 base field (stored in Montgomery form)
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Fp([u64; 4]);
+
+impl Fp {
+    const ZERO: Self = Self([0; 4]);
+    const fn from_limbs(a: &[u64; 4]) -> Self {
+        Self(mont_mul(a, &R2))
+    }
+    fn from_be(b: &[u8]) -> Option<Self> {
+        let a = limbs_from_be(b)?;
+        if !sub(&a, &P).1 {
+            return None;
+        }
+        Some(Self::from_limbs(&a))
+    }
+    /// Interprets a big-endian byte string of any length modulo `P`
+    fn from_be_reduced(b: &[u8]) -> Self {
+        let base = Self::from_limbs(&[256, 0, 0, 0]);
+        b.iter().fold(Self::ZERO, |acc, &byte| {
+            acc.mul(&base)
+                .add(&Self::from_limbs(&[byte.into(), 0, 0, 0]))
+        })
+    }
+    fn to_be(self) -> [u8; SIZE] {
+        let a = mont_mul(&self.0, &[1, 0, 0, 0]);
+        let mut r = [0u8; SIZE];
+        for (chunk, limb) in r.rchunks_exact_mut(8).zip(a) {
+            chunk.copy_from_slice(&limb.to_be_bytes());
+        }
+        r
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self(add_mod(&self.0, &other.0, &P))
+    }
+    fn sub(&self, other: &Self) -> Self {
+        Self(sub_mod(&self.0, &other.0, &P))
+    }
+    fn neg(&self) -> Self {
+        Self::ZERO.sub(self)
+    }
+    fn mul(&self, other: &Self) -> Self {
+        Self(mont_mul(&self.0, &other.0))
+    }
+    fn square(&self) -> Self {
+        self.mul(self)
+    }
+    fn pow(&self, exp: &[u64; 4]) -> Self {
+        let mut r = Self::from_limbs(&[1, 0, 0, 0]);
+        for limb in exp.iter().rev() {
+            for bit in (0..64).rev() {
+                r = r.square();
+                if (limb >> bit) & 1 == 1 {
+                    r = r.mul(self);
+                }
+            }
+        }
+        r
+    }
+    fn invert(&self) -> Self {
+        self.pow(&P_MINUS_2)
+    }
+    fn sqrt(&self) -> Option<Self> {
+        let r = self.pow(&P_PLUS_1_DIV_4);
+        (r.square() == *self).then_some(r)
+    }
+}
+
+impl fmt::Debug for Fp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x")?;
+        for b in self.to_be() {
+            write!(f, "{b:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `x^3 + 3`, the right hand side of the curve equation
+fn curve_rhs(x: &Fp) -> Fp {
+    let three = Fp::from_limbs(&[3, 0, 0, 0]);
+    x.square().mul(x).add(&three)
+}
+
+/// A point on the BN_P256 curve `y^2 = x^3 + 3`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Point(Option<(Fp, Fp)>);
+
+impl Point {
+    /// The point at infinity (the identity element)
+    pub const INFINITY: Self = Self(None);
+    /// The generator `(1, 2)` of the curve
+    pub const GENERATOR: Self = Self(Some((
+        Fp::from_limbs(&[1, 0, 0, 0]),
+        Fp::from_limbs(&[2, 0, 0, 0]),
+    )));
+
+    /// Creates a point from big-endian coordinates, checking that it is on the
+    /// curve.
+    pub fn new(x: &[u8], y: &[u8]) -> Option<Self> {
+        let (x, y) = (Fp::from_be(x)?, Fp::from_be(y)?);
+        (y.square() == curve_rhs(&x)).then_some(Self(Some((x, y))))
+    }
+
+    /// Creates a point from a TPMS_ECC_POINT (e.g. from [`CommitResponse`]).
+    ///
+    /// [`CommitResponse`]: crate::commands::CommitResponse
+    pub fn from_ecc_point(p: &tpms::EccPoint<'_>) -> Option<Self> {
+        Self::new(p.x, p.y)
+    }
+
+    /// Maps a digest onto the curve the same way as TPM2_Commit.
+    ///
+    /// The TPM computes `x = H(s2) mod p`, so `digest` should be the hash of
+    /// `s2` using the signing key's name algorithm. The `y` coordinate of the
+    /// result is the `y2` parameter for [`Commit`]. Returns `None` if there is
+    /// no point with this `x` coordinate, in which case the caller should
+    /// choose a different `s2`.
+    ///
+    /// [`Commit`]: crate::commands::Commit
+    pub fn from_digest(digest: &[u8]) -> Option<Self> {
+        let x = Fp::from_be_reduced(digest);
+        let y = curve_rhs(&x).sqrt()?;
+        Some(Self(Some((x, y))))
+    }
+
+    /// Returns the big-endian `(x, y)` coordinates, or `None` for the point at
+    /// infinity.
+    pub fn coordinates(&self) -> Option<([u8; SIZE], [u8; SIZE])> {
+        self.0.map(|(x, y)| (x.to_be(), y.to_be()))
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn neg(&self) -> Self {
+        Self(self.0.map(|(x, y)| (x, y.neg())))
+    }
+
+    pub fn double(&self) -> Self {
+        self.add(self)
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let ((x1, y1), (x2, y2)) = match (self.0, other.0) {
+            (None, _) => return *other,
+            (_, None) => return *self,
+            (Some(p1), Some(p2)) => (p1, p2),
+        };
+        let lambda = if x1 == x2 {
+            if y1 != y2 || y1 == Fp::ZERO {
+                return Self::INFINITY;
+            }
+            // Tangent slope: 3x^2 / 2y
+            let x_sq = x1.square();
+            x_sq.add(&x_sq).add(&x_sq).mul(&y1.add(&y1).invert())
+        } else {
+            y2.sub(&y1).mul(&x2.sub(&x1).invert())
+        };
+        let x3 = lambda.square().sub(&x1).sub(&x2);
+        let y3 = lambda.mul(&x1.sub(&x3)).sub(&y1);
+        Self(Some((x3, y3)))
+    }
+
+    /// Multiplies this point by a big-endian scalar of any length
+    pub fn mul(&self, scalar: &[u8]) -> Self {
+        let mut r = Self::INFINITY;
+        for byte in scalar {
+            for bit in (0..8).rev() {
+                r = r.double();
+                if (byte >> bit) & 1 == 1 {
+                    r = r.add(self);
+                }
+            }
+        }
+        r
+    }
+}
+
+/// Returns the big-endian order of the BN_P256 group
+pub fn order() -> [u8; SIZE] {
+    let mut r = [0u8; SIZE];
+    for (chunk, limb) in r.rchunks_exact_mut(8).zip(N) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+    r
+}
+
+/// An ECDAA credential `(A, B, C, D)` issued for a TPM key
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Credential {
+    pub a: Point,
+    pub b: Point,
+    pub c: Point,
+    pub d: Point,
+}
+
+impl Credential {
+    /// Randomizes the credential by multiplying each point by `l`.
+    ///
+    /// This produces `(R, S, T, W)`. The `S` value (the randomized `b`) is
+    /// then passed as `p1` to [`Commit`]. The scalar `l` must be chosen
+    /// uniformly at random from `[1, n)`, see [`order`].
+    ///
+    /// [`Commit`]: crate::commands::Commit
+    pub fn randomize(&self, l: &[u8]) -> Self {
+        Self {
+            a: self.a.mul(l),
+            b: self.b.mul(l),
+            c: self.c.mul(l),
+            d: self.d.mul(l),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn group_order() {
+        let g = Point::GENERATOR;
+        assert!(g.mul(&order()).is_infinity());
+
+        let mut n_minus_1 = order();
+        n_minus_1[SIZE - 1] -= 1;
+        assert_eq!(g.mul(&n_minus_1), g.neg());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let g = Point::GENERATOR;
+        assert_eq!(g.mul(&[3]), g.double().add(&g));
+        assert_eq!(g.mul(&[5]).add(&g.mul(&[7])), g.mul(&[12]));
+        assert!(g.add(&g.neg()).is_infinity());
+
+        let (x, y) = g.mul(&[42]).coordinates().unwrap();
+        assert_eq!(Point::new(&x, &y), Some(g.mul(&[42])));
+        assert_eq!(Point::new(&x, &[1]), None);
+    }
+
+    #[test]
+    fn from_digest() {
+        let g = Point::GENERATOR;
+        let p = Point::from_digest(&[1]).unwrap();
+        assert!(p == g || p == g.neg());
+
+        // Digests larger than the modulus are reduced: P + 1 = 1 mod P
+        let mut digest = [0u8; 48];
+        digest[16..].copy_from_slice(&[
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFC, 0xF0, 0xCD, 0x46, 0xE5, 0xF2, 0x5E, 0xEE, 0x71,
+            0xA4, 0x9F, 0x0C, 0xDC, 0x65, 0xFB, 0x12, 0x98, 0x0A, 0x82, 0xD3, 0x29, 0x2D, 0xDB,
+            0xAE, 0xD3, 0x30, 0x14,
+        ]);
+        assert_eq!(Point::from_digest(&digest), Some(p));
+    }
+}
@@ -1,26 +1,96 @@
 //! Error types for various TPM-related operations
 
-use core::num::{NonZeroU32, TryFromIntError};
+use core::{
+    fmt,
+    num::{NonZeroU32, TryFromIntError},
+};
+
+use crate::types::{
+    tpm::{self, rc},
+    Handle,
+};
+
+/// Error returned when running TPM commands
+///
+/// Errors from [`TpmRun::run`](crate::TpmRun::run) carry a [`Context`]
+/// describing which command was running and where it failed.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    context: Option<Context>,
+}
 
 #[derive(Debug)]
 #[non_exhaustive]
-pub enum Error {
+pub enum ErrorKind {
     Tpm(TpmError),
     Marshal(MarshalError),
     Unmarshal(UnmarshalError),
     Driver(DriverError),
+    Auth(AuthError),
     TooManyAuths(usize),
+    /// The PCRs kept changing while
+    /// [`TpmExt::read_all_pcrs`](crate::TpmExt::read_all_pcrs) was reading them
+    PcrsChanged,
+    NvType(NvTypeError),
 }
 
+/// The command associated with an [`Error`]
 #[derive(Clone, Copy, Debug)]
+pub struct Context {
+    pub command: tpm::CC,
+    /// The part of the command or response which caused the error. For TPM
+    /// errors, this is determined from the handle, session or parameter
+    /// number in the response code.
+    pub part: Option<Part>,
+    /// For marshalling and unmarshalling errors, the offset (in bytes) into
+    /// the command or response at which processing failed
+    pub offset: Option<usize>,
+    /// For marshalling and unmarshalling errors, the innermost structure
+    /// and field being processed (e.g. `"tpms::Context.context_blob"`)
+    pub field: Option<&'static str>,
+}
+
+/// A section of a TPM command or response
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Part {
+    CommandHeader,
+    CommandHandles,
+    CommandAuths,
+    CommandParams,
+    ResponseHeader,
+    ResponseHandles,
+    ResponseAuths,
+    ResponseParams,
+}
+
+/// A non-zero TPM_RC returned by the TPM
+///
+/// The `Debug` and `Display` implementations decode the response code, e.g.
+/// `0x1C4` is displayed as `TPM_RC_VALUE (parameter 1)`.
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct TpmError(pub NonZeroU32);
 
+/// The part of a command which caused a format-one [`TpmError`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RcLocation {
+    /// The Nth parameter (starting at 1)
+    Parameter(u8),
+    /// The Nth handle (starting at 1)
+    Handle(u8),
+    /// The Nth session (starting at 1)
+    Session(u8),
+}
+
 #[derive(Clone, Copy, Debug)]
 #[non_exhaustive]
 pub enum MarshalError {
     BufferOverflow,
     IntegerOverflow,
+    /// The size in a raw command's header does not match its length
+    SizeMismatch,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -29,36 +99,360 @@ pub enum UnmarshalError {
     BufferOverflow,
     BufferRemaining,
     InvalidValue,
-    PcrTooLarge(usize),
+    /// The size in the response header does not match the response length
+    SizeMismatch,
+    /// The response tag does not match the command tag
+    TagMismatch,
+}
+
+/// Error returned by [`Auth::set_auth`](crate::types::Auth::set_auth) when a
+/// session in the response is invalid
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum AuthError {
+    InvalidNonce,
+    InvalidAttributes,
+    InvalidHmac,
 }
 
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum DriverError {
     IntegerOverflow,
+    /// A command run internally by a wrapper (such as
+    /// [`ResourceManager`](crate::wrap::ResourceManager)) could not be
+    /// marshalled, or its response could not be unmarshalled.
+    InvalidResponse,
     #[cfg(feature = "std")]
     #[doc(cfg(feature = "std"))]
     Io(std::io::Error),
 }
 
+/// Error returned by [`TpmExt::take_ownership`](crate::TpmExt::take_ownership)
+#[derive(Debug)]
+pub struct OwnershipError {
+    /// The hierarchy whose authorization value could not be set
+    pub hierarchy: Handle,
+    pub error: Error,
+}
+
+/// Error returned when opening an NV Index of the wrong type with one of the
+/// [`nv`](crate::nv) wrappers
+#[derive(Clone, Copy, Debug)]
+pub struct NvTypeError {
+    pub nv_index: Handle,
+    pub expected: tpm::NT,
+    /// The type in the index's attributes, or `None` if it is not a known
+    /// TPM_NT value
+    pub actual: Option<tpm::NT>,
+}
+
+impl TpmError {
+    /// The raw TPM_RC value
+    pub const fn get(self) -> u32 {
+        self.0.get()
+    }
+
+    /// Format-one codes carry a parameter, handle or session number.
+    pub const fn is_format_one(self) -> bool {
+        self.get() & rc::FMT1 != 0
+    }
+
+    /// Warnings indicate that the command was not run, but could succeed
+    /// later (e.g. [`rc::TESTING`] or [`rc::RETRY`]).
+    pub const fn is_warning(self) -> bool {
+        !self.is_format_one() && self.get() & rc::WARN == rc::WARN
+    }
+
+    /// Vendor-defined codes cannot be decoded by this crate.
+    pub const fn is_vendor(self) -> bool {
+        !self.is_format_one() && self.get() & rc::VENDOR != 0
+    }
+
+    /// The response code without any parameter, handle or session number,
+    /// suitable for comparing with the constants in [`rc`].
+    pub const fn base(self) -> u32 {
+        if self.is_format_one() {
+            self.get() & !(rc::N_MASK | rc::P)
+        } else {
+            self.get()
+        }
+    }
+
+    /// For format-one codes, the parameter, handle or session which caused
+    /// the error (if the TPM reported one).
+    pub const fn location(self) -> Option<RcLocation> {
+        if !self.is_format_one() {
+            return None;
+        }
+        let n = ((self.get() & rc::N_MASK) >> 8) as u8;
+        if self.get() & rc::P != 0 {
+            Some(RcLocation::Parameter(n))
+        } else if n == 0 {
+            None
+        } else if self.get() & rc::S != 0 {
+            Some(RcLocation::Session(n & 0x7))
+        } else {
+            Some(RcLocation::Handle(n))
+        }
+    }
+
+    /// The name of the response code (e.g. `"TPM_RC_VALUE"`), if known
+    pub const fn name(self) -> Option<&'static str> {
+        if self.is_vendor() {
+            return None;
+        }
+        rc::name(self.base())
+    }
+
+    /// Returns true if the same command might succeed if it is resent later.
+    pub const fn is_retryable(self) -> bool {
+        matches!(
+            self.base(),
+            rc::YIELDED | rc::CANCELED | rc::TESTING | rc::NV_RATE | rc::RETRY | rc::NV_UNAVAILABLE
+        )
+    }
+
+    /// Returns true if the TPM is in Dictionary Attack lockout.
+    pub const fn is_lockout(self) -> bool {
+        self.base() == rc::LOCKOUT
+    }
+
+    /// Returns true if an authorization value or HMAC was incorrect.
+    pub const fn is_auth_fail(self) -> bool {
+        matches!(self.base(), rc::AUTH_FAIL | rc::BAD_AUTH)
+    }
+}
+
+impl fmt::Display for TpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name)?,
+            None if self.is_vendor() => write!(f, "vendor TPM_RC {:#05X}", self.base())?,
+            None => write!(f, "unknown TPM_RC {:#05X}", self.base())?,
+        }
+        match self.location() {
+            Some(RcLocation::Parameter(n)) => write!(f, " (parameter {n})"),
+            Some(RcLocation::Handle(n)) => write!(f, " (handle {n})"),
+            Some(RcLocation::Session(n)) => write!(f, " (session {n})"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for TpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TpmError({:#X}: {self})", self.get())
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The command which failed, if this error came from running a command
+    pub fn context(&self) -> Option<&Context> {
+        self.context.as_ref()
+    }
+
+    /// The response code, if this error was returned by the TPM
+    pub fn tpm_error(&self) -> Option<TpmError> {
+        match self.kind {
+            ErrorKind::Tpm(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Attaches `context`, replacing any existing context.
+    pub(crate) fn with_context(mut self, context: Context) -> Self {
+        self.context = Some(context);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)?;
+        match self.context {
+            Some(ctx) => write!(f, ", in {ctx}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tpm(e) => write!(f, "TPM returned {e}"),
+            Self::Marshal(e) => e.fmt(f),
+            Self::Unmarshal(e) => e.fmt(f),
+            Self::Driver(e) => e.fmt(f),
+            Self::Auth(e) => e.fmt(f),
+            Self::TooManyAuths(n) => write!(f, "too many authorizations ({n}) for command"),
+            Self::PcrsChanged => f.write_str("PCRs changed during every attempt to read them"),
+            Self::NvType(e) => e.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.command)?;
+        if let Some(part) = self.part {
+            f.write_str(match part {
+                Part::CommandHeader => " command header",
+                Part::CommandHandles => " command handles",
+                Part::CommandAuths => " command sessions",
+                Part::CommandParams => " command parameters",
+                Part::ResponseHeader => " response header",
+                Part::ResponseHandles => " response handles",
+                Part::ResponseAuths => " response sessions",
+                Part::ResponseParams => " response parameters",
+            })?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {offset}")?;
+        }
+        match self.field {
+            Some(field) => write!(f, " ({field})"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Context {
+    /// Context for an error not associated with any part of `command`
+    pub(crate) const fn for_command(command: tpm::CC) -> Self {
+        Self {
+            command,
+            part: None,
+            offset: None,
+            field: None,
+        }
+    }
+
+    /// Context for `e`, returned by the TPM when running `command`
+    pub(crate) const fn for_tpm_error(command: tpm::CC, e: TpmError) -> Self {
+        let part = match e.location() {
+            Some(RcLocation::Parameter(_)) => Some(Part::CommandParams),
+            Some(RcLocation::Handle(_)) => Some(Part::CommandHandles),
+            Some(RcLocation::Session(_)) => Some(Part::CommandAuths),
+            None => None,
+        };
+        Self {
+            command,
+            part,
+            offset: None,
+            field: None,
+        }
+    }
+}
+
+impl fmt::Display for MarshalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::BufferOverflow => "command does not fit in buffer",
+            Self::IntegerOverflow => "value too large to marshal",
+            Self::SizeMismatch => "command size does not match its header",
+        })
+    }
+}
+
+impl fmt::Display for UnmarshalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferOverflow => f.write_str("response is truncated"),
+            Self::BufferRemaining => f.write_str("response has trailing data"),
+            Self::InvalidValue => f.write_str("response contains an invalid value"),
+            Self::SizeMismatch => f.write_str("response size does not match its header"),
+            Self::TagMismatch => f.write_str("response tag does not match the command"),
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidNonce => "invalid nonce in response session",
+            Self::InvalidAttributes => "invalid attributes in response session",
+            Self::InvalidHmac => "invalid HMAC in response session",
+        })
+    }
+}
+
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IntegerOverflow => f.write_str("buffer size too large for driver"),
+            Self::InvalidResponse => f.write_str("invalid response to internal command"),
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "TPM I/O error: {e}"),
+        }
+    }
+}
+
+impl fmt::Display for OwnershipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to change authorization of hierarchy {:#010X}: {}",
+            self.hierarchy, self.error
+        )
+    }
+}
+
+impl fmt::Display for NvTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NV Index {:#010X} has type ", self.nv_index)?;
+        match self.actual {
+            Some(nt) => write!(f, "{nt:?}")?,
+            None => f.write_str("unknown")?,
+        }
+        write!(f, ", expected {:?}", self.expected)
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            context: None,
+        }
+    }
+}
 impl From<TpmError> for Error {
     fn from(e: TpmError) -> Self {
-        Self::Tpm(e)
+        ErrorKind::Tpm(e).into()
     }
 }
 impl From<MarshalError> for Error {
     fn from(e: MarshalError) -> Self {
-        Self::Marshal(e)
+        ErrorKind::Marshal(e).into()
     }
 }
 impl From<UnmarshalError> for Error {
     fn from(e: UnmarshalError) -> Self {
-        Self::Unmarshal(e)
+        ErrorKind::Unmarshal(e).into()
     }
 }
 impl From<DriverError> for Error {
     fn from(e: DriverError) -> Self {
-        Self::Driver(e)
+        ErrorKind::Driver(e).into()
+    }
+}
+impl From<AuthError> for Error {
+    fn from(e: AuthError) -> Self {
+        ErrorKind::Auth(e).into()
+    }
+}
+impl From<NvTypeError> for Error {
+    fn from(e: NvTypeError) -> Self {
+        ErrorKind::NvType(e).into()
     }
 }
 
@@ -78,12 +472,42 @@ impl From<TryFromIntError> for DriverError {
 mod std_impl {
     use super::*;
 
-    // impl std::error::Error for Error {}
-    // impl std::error::Error for TpmError {}
-    // impl std::error::Error for MarshalError {}
-    // impl std::error::Error for UnmarshalError {}
-    // impl std::error::Error for AuthError {}
-    // impl std::error::Error for DriverError {}
+    impl std::error::Error for Error {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            self.kind.source()
+        }
+    }
+    impl std::error::Error for ErrorKind {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::Tpm(e) => Some(e),
+                Self::Marshal(e) => Some(e),
+                Self::Unmarshal(e) => Some(e),
+                Self::Driver(e) => Some(e),
+                Self::Auth(e) => Some(e),
+                Self::NvType(e) => Some(e),
+                Self::TooManyAuths(_) | Self::PcrsChanged => None,
+            }
+        }
+    }
+    impl std::error::Error for TpmError {}
+    impl std::error::Error for MarshalError {}
+    impl std::error::Error for UnmarshalError {}
+    impl std::error::Error for AuthError {}
+    impl std::error::Error for NvTypeError {}
+    impl std::error::Error for DriverError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::IntegerOverflow | Self::InvalidResponse => None,
+                Self::Io(e) => Some(e),
+            }
+        }
+    }
+    impl std::error::Error for OwnershipError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.error)
+        }
+    }
 
     impl From<std::io::Error> for DriverError {
         fn from(err: std::io::Error) -> Self {
@@ -91,3 +515,65 @@ mod std_impl {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::string::ToString;
+
+    use super::*;
+
+    fn err(rc: u32) -> TpmError {
+        TpmError(NonZeroU32::new(rc).unwrap())
+    }
+
+    #[test]
+    fn decode_rc() {
+        let e = err(0x1C4);
+        assert!(e.is_format_one());
+        assert_eq!(e.base(), rc::VALUE);
+        assert_eq!(e.location(), Some(RcLocation::Parameter(1)));
+        assert_eq!(e.to_string(), "TPM_RC_VALUE (parameter 1)");
+
+        let e = err(0x98E);
+        assert!(e.is_auth_fail() && !e.is_warning());
+        assert_eq!(e.location(), Some(RcLocation::Session(1)));
+        assert_eq!(e.to_string(), "TPM_RC_AUTH_FAIL (session 1)");
+
+        let e = err(0x28B);
+        assert_eq!(e.location(), Some(RcLocation::Handle(2)));
+        assert_eq!(e.to_string(), "TPM_RC_HANDLE (handle 2)");
+
+        let e = err(rc::LOCKOUT);
+        assert!(e.is_warning() && e.is_lockout() && !e.is_retryable());
+        assert_eq!(e.location(), None);
+        assert_eq!(e.to_string(), "TPM_RC_LOCKOUT");
+
+        assert!(err(rc::TESTING).is_retryable());
+        assert!(!err(rc::INITIALIZE).is_warning());
+        assert_eq!(err(rc::INITIALIZE).name(), Some("TPM_RC_INITIALIZE"));
+        assert_eq!(err(0x501).to_string(), "vendor TPM_RC 0x501");
+        assert_eq!(err(0x17F).to_string(), "unknown TPM_RC 0x17F");
+    }
+
+    #[test]
+    fn error_context() {
+        let e = Error::from(err(0x1C4));
+        assert_eq!(e.to_string(), "TPM returned TPM_RC_VALUE (parameter 1)");
+        let e = e.with_context(Context::for_tpm_error(tpm::CC::NvRead, err(0x1C4)));
+        assert_eq!(
+            e.to_string(),
+            "TPM returned TPM_RC_VALUE (parameter 1), in NvRead command parameters"
+        );
+
+        let e = Error::from(UnmarshalError::BufferOverflow).with_context(Context {
+            command: tpm::CC::GetRandom,
+            part: Some(Part::ResponseParams),
+            offset: Some(12),
+            field: None,
+        });
+        assert_eq!(
+            e.to_string(),
+            "response is truncated, in GetRandom response parameters at byte 12"
+        );
+    }
+}
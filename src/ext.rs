@@ -1,9 +1,54 @@
-use crate::{commands::GetRandom, Error, TpmRun};
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::time::Duration;
+
+use crate::{
+    commands::{
+        GetCapability, GetRandom, GetTestResult, HierarchyChangeAuth, IncrementalSelfTest, NvWrite,
+        PcrAllocate, PcrAllocateResponse, TestParms,
+    },
+    error::{MarshalError, OwnershipError, UnmarshalError},
+    run::execute_raw,
+    types::{tpm, tpma, tpmi, tpms, tpmt, Auth, AuthHandle},
+    wrap::backoff,
+    Error, TpmRun,
+};
+#[cfg(feature = "alloc")]
+use crate::{
+    commands::{NvRead, NvReadPublic, PcrRead, PcrReadResponse},
+    error::ErrorKind,
+};
+
+/// Maximum number of PCR banks (one per variant of [`tpmt::Hash`])
+///
+/// [`tpmt::Hash`]: crate::types::tpmt::Hash
+const MAX_BANKS: usize = 8;
+
+/// Maximum number of times [`TpmExt::ensure_tested`] polls for the test result
+const MAX_TEST_POLLS: u32 = 20;
+const TEST_POLL_DELAY: Duration = Duration::from_millis(10);
+const TEST_POLL_MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Number of times [`TpmExt::read_all_pcrs`] restarts before giving up
+#[cfg(feature = "alloc")]
+const MAX_PCR_READS: usize = 8;
 
 /// Trait extending [`Tpm`](crate::Tpm) for running higher-level TPM workflows.
 ///
 /// These methods almost always issues multiple TPM commands under the hood.
 pub trait TpmExt: TpmRun {
+    /// Sends the already marshalled command `cmd` to the TPM exactly as-is,
+    /// returning the entire response (including the header).
+    ///
+    /// Only the framing is checked: the size in the header of both the command
+    /// and the response must match its length. In particular, TPM error codes
+    /// are returned in the response rather than as an [`Error`], and commands
+    /// are never retried.
+    fn run_raw(&mut self, cmd: &[u8]) -> Result<&[u8], Error> {
+        execute_raw(self, cmd)?;
+        Ok(self.response_buf())
+    }
+
     fn getrandom(&mut self, mut buf: &mut [u8]) -> Result<(), Error> {
         while !buf.is_empty() {
             let bytes_requested = buf.len().try_into().unwrap_or(u16::MAX);
@@ -15,6 +60,886 @@ pub trait TpmExt: TpmRun {
         }
         Ok(())
     }
+
+    /// Enables and disables entire PCR banks, leaving other banks unchanged.
+    ///
+    /// The current allocation is read with TPM2_GetCapability and the new
+    /// allocation is set with [`PcrAllocate`], which requires Platform
+    /// Authorization. The change only takes effect after the next TPM Reset.
+    /// If an algorithm is in both `enable` and `disable`, it is disabled.
+    fn pcr_allocate(
+        &mut self,
+        platform_auth: &dyn Auth,
+        enable: &[tpmi::AlgHash],
+        disable: &[tpmi::AlgHash],
+    ) -> Result<PcrAllocateResponse, Error> {
+        let rsp = self.run(GetCapability {
+            capability: tpm::Cap::Pcrs,
+            property: 0,
+            property_count: 1,
+        })?;
+        let tpms::CapabilityData::Pcrs(current) = rsp.capability_data else {
+            return Err(UnmarshalError::InvalidValue.into());
+        };
+
+        let mut banks = [tpms::PcrSelection::default(); MAX_BANKS];
+        let banks = reallocate(current, enable, disable, &mut banks)?;
+
+        let rsp = self.run(PcrAllocate {
+            auth_handle: AuthHandle {
+                handle: tpm::rh::PLATFORM,
+                auth: platform_auth,
+            },
+            pcr_allocation: banks.into(),
+        })?;
+        Ok(rsp)
+    }
+
+    /// Reads every PCR in every allocated PCR bank.
+    ///
+    /// The allocated banks are queried with TPM2_GetCapability, and
+    /// [`PcrRead`] is issued repeatedly, as a single read returns at most 8
+    /// digests. If the PCRs change during the read (i.e. the
+    /// `pcr_update_counter` changes), the entire read is restarted, up to
+    /// a limit after which [`ErrorKind::PcrsChanged`] is returned.
+    #[cfg(feature = "alloc")]
+    #[doc(cfg(feature = "alloc"))]
+    fn read_all_pcrs(&mut self) -> Result<Vec<PcrBank>, Error> {
+        'retry: for _ in 0..MAX_PCR_READS {
+            let rsp = self.run(GetCapability {
+                capability: tpm::Cap::Pcrs,
+                property: 0,
+                property_count: 1,
+            })?;
+            let tpms::CapabilityData::Pcrs(current) = rsp.capability_data else {
+                return Err(UnmarshalError::InvalidValue.into());
+            };
+
+            let mut remaining = [tpms::PcrSelection::default(); MAX_BANKS];
+            let mut len = 0;
+            for sel in current {
+                *remaining.get_mut(len).ok_or(UnmarshalError::InvalidValue)? = sel;
+                len += 1;
+            }
+            let remaining = &mut remaining[..len];
+            let mut banks: Vec<PcrBank> = remaining
+                .iter()
+                .map(|sel| PcrBank {
+                    hash: sel.hash,
+                    pcrs: BTreeMap::new(),
+                })
+                .collect();
+
+            let mut counter = None;
+            while remaining.iter().any(|sel| !sel.select.is_empty()) {
+                let rsp = self.run(PcrRead {
+                    pcr_selection: (&*remaining).into(),
+                })?;
+                if *counter.get_or_insert(rsp.pcr_update_counter) != rsp.pcr_update_counter {
+                    continue 'retry;
+                }
+                // The TPM returns nothing for PCRs it does not implement.
+                if !record_pcrs(rsp, remaining, &mut banks)? {
+                    break;
+                }
+            }
+            return Ok(banks);
+        }
+        Err(ErrorKind::PcrsChanged.into())
+    }
+
+    /// Reads the entire contents of an NV Index.
+    ///
+    /// The size of the index is read with [`NvReadPublic`], and the data is
+    /// read with as many [`NvRead`] commands as needed, each transferring at
+    /// most TPM_PT_NV_BUFFER_MAX bytes. The `auth_handle` is either the
+    /// index itself, [`tpm::rh::OWNER`], or [`tpm::rh::PLATFORM`].
+    #[cfg(feature = "alloc")]
+    #[doc(cfg(feature = "alloc"))]
+    fn nv_read_all(
+        &mut self,
+        auth_handle: AuthHandle,
+        nv_index: tpmi::RhNvIndex,
+    ) -> Result<Vec<u8>, Error> {
+        let size = self.run(NvReadPublic { nv_index })?.nv_public.0.data_size;
+        let max = nv_buffer_max(self)?;
+
+        let mut data = Vec::with_capacity(size.into());
+        let mut offset = 0;
+        while offset < size {
+            let chunk = (size - offset).min(max);
+            let rsp = self.run(NvRead {
+                auth_handle,
+                nv_index,
+                size: chunk,
+                offset,
+            })?;
+            if rsp.data.len() != usize::from(chunk) {
+                return Err(UnmarshalError::InvalidValue.into());
+            }
+            data.extend_from_slice(rsp.data);
+            offset += chunk;
+        }
+        Ok(data)
+    }
+
+    /// Writes `data` to the start of an NV Index.
+    ///
+    /// The data is written with as many [`NvWrite`] commands as needed, each
+    /// transferring at most TPM_PT_NV_BUFFER_MAX bytes. The `auth_handle` is
+    /// either the index itself, [`tpm::rh::OWNER`], or [`tpm::rh::PLATFORM`].
+    fn nv_write_all(
+        &mut self,
+        auth_handle: AuthHandle,
+        nv_index: tpmi::RhNvIndex,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        u16::try_from(data.len()).map_err(MarshalError::from)?;
+        let max = nv_buffer_max(self)?;
+
+        for (i, chunk) in data.chunks(max.into()).enumerate() {
+            let offset = (i * usize::from(max))
+                .try_into()
+                .map_err(MarshalError::from)?;
+            self.run(NvWrite {
+                auth_handle,
+                nv_index,
+                data: chunk,
+                offset,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Sets the authorization values of the Owner, Endorsement and Lockout
+    /// hierarchies (in that order) with [`HierarchyChangeAuth`].
+    ///
+    /// `current_auth` authorizes all three changes. On a newly cleared TPM,
+    /// this is an empty [`PasswordAuth`](crate::types::PasswordAuth). If a
+    /// step fails, the error contains the hierarchy that could not be
+    /// changed. Hierarchies earlier in the sequence keep their new values.
+    fn take_ownership(
+        &mut self,
+        current_auth: &dyn Auth,
+        owner_auth: &[u8],
+        endorsement_auth: &[u8],
+        lockout_auth: &[u8],
+    ) -> Result<(), OwnershipError> {
+        let steps = [
+            (tpm::rh::OWNER, owner_auth),
+            (tpm::rh::ENDORSEMENT, endorsement_auth),
+            (tpm::rh::LOCKOUT, lockout_auth),
+        ];
+        for (hierarchy, new_auth) in steps {
+            let auth_handle = AuthHandle {
+                handle: hierarchy,
+                auth: current_auth,
+            };
+            self.run(HierarchyChangeAuth {
+                auth_handle,
+                new_auth,
+            })
+            .map_err(|error| OwnershipError { hierarchy, error })?;
+        }
+        Ok(())
+    }
+
+    /// Tests the algorithms in `algs`, so that they can be used without the
+    /// TPM returning TPM_RC_TESTING.
+    ///
+    /// The tests are started with [`IncrementalSelfTest`] (algorithms that
+    /// were already tested are skipped). If any tests are still to be run,
+    /// [`GetTestResult`] is polled until they finish, calling `sleep` (e.g.
+    /// [`std::thread::sleep`]) between polls with the same backoff as
+    /// [`Retry`](crate::wrap::Retry). If a test fails, the TPM's test result
+    /// is returned as an error, as is TPM_RC_TESTING if the tests have not
+    /// finished after 20 polls (about 13 seconds).
+    fn ensure_tested(&mut self, algs: &[tpm::Alg], sleep: fn(Duration)) -> Result<(), Error> {
+        let rsp = self.run(IncrementalSelfTest {
+            to_test: algs.into(),
+        })?;
+        if rsp.to_do_list.is_empty() {
+            return Ok(());
+        }
+        let mut polls = 0;
+        loop {
+            polls += 1;
+            match self.run(GetTestResult {})?.test_result {
+                None => return Ok(()),
+                Some(e) if e.base() == tpm::rc::TESTING && polls < MAX_TEST_POLLS => {
+                    sleep(backoff(TEST_POLL_DELAY, TEST_POLL_MAX_DELAY, polls));
+                }
+                Some(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Returns true if the TPM supports creating objects with `parameters`.
+    ///
+    /// The parameters are checked with [`TestParms`]. If the TPM rejects
+    /// a parameter (e.g. with TPM_RC_KEY_SIZE or TPM_RC_CURVE), they are
+    /// unsupported. Any other error, including warnings such as
+    /// TPM_RC_TESTING or TPM_RC_RETRY, is returned.
+    fn supports(&mut self, parameters: &tpmt::PublicParms) -> Result<bool, Error> {
+        use tpm::rc;
+        match self.run(TestParms {
+            parameters: *parameters,
+        }) {
+            Ok(()) => Ok(true),
+            Err(e) => match e.tpm_error().map(|e| e.base()) {
+                Some(
+                    rc::VALUE
+                    | rc::KEY_SIZE
+                    | rc::CURVE
+                    | rc::SCHEME
+                    | rc::MODE
+                    | rc::SYMMETRIC
+                    | rc::HASH
+                    | rc::ASYMMETRIC
+                    | rc::KDF,
+                ) => Ok(false),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Probes which common algorithms and parameters the TPM supports.
+    ///
+    /// This runs [`TpmExt::supports`] for each RSA key size, ECC curve,
+    /// symmetric cipher/mode and hash algorithm listed in the returned
+    /// [`AlgorithmSupport`].
+    #[cfg(feature = "alloc")]
+    #[doc(cfg(feature = "alloc"))]
+    fn algorithm_support(&mut self) -> Result<AlgorithmSupport, Error> {
+        let mut report = AlgorithmSupport::default();
+        for key_bits in PROBE_RSA_KEY_BITS {
+            let parms = tpmt::PublicParms::Rsa(tpms::RsaParms {
+                key_bits,
+                ..Default::default()
+            });
+            if self.supports(&parms)? {
+                report.rsa_key_bits.push(key_bits);
+            }
+        }
+        for curve_id in PROBE_ECC_CURVES {
+            let parms = tpmt::PublicParms::Ecc(tpms::EccParms {
+                curve_id,
+                ..Default::default()
+            });
+            if self.supports(&parms)? {
+                report.ecc_curves.push(curve_id);
+            }
+        }
+        for (algorithm, key_bits) in PROBE_SYM_CIPHERS {
+            for mode in PROBE_SYM_MODES {
+                let sym = tpmt::SymDefObject {
+                    algorithm,
+                    key_bits,
+                    mode,
+                };
+                if self.supports(&tpmt::PublicParms::SymCipher(sym))? {
+                    report.sym_ciphers.push(sym);
+                }
+            }
+        }
+        for hash in PROBE_HASHES {
+            let scheme = tpmt::KeyedHashScheme::Hmac(hash);
+            if self.supports(&tpmt::PublicParms::KeyedHash(Some(scheme)))? {
+                report.hashes.push(hash);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Reads the current dictionary attack (DA) state of the TPM.
+    ///
+    /// If [`DaStatus::in_lockout`] is set, commands requiring DA-protected
+    /// authorization fail with TPM_RC_LOCKOUT until the lockout interval
+    /// expires or [`DictionaryAttackLockReset`] is run.
+    ///
+    /// [`DictionaryAttackLockReset`]: crate::commands::DictionaryAttackLockReset
+    fn da_status(&mut self) -> Result<DaStatus, Error> {
+        // LOCKOUT_COUNTER through LOCKOUT_RECOVERY are read in one command, so
+        // they are consistent with each other.
+        let mut da = [0; 4];
+        get_tpm_properties(self, tpm::pt::LOCKOUT_COUNTER, &mut da)?;
+        let [lockout_counter, max_auth_fail, lockout_interval, lockout_recovery] = da;
+        let permanent = get_tpm_property(self, tpm::pt::PERMANENT)?;
+        Ok(DaStatus {
+            lockout_counter,
+            max_auth_fail,
+            lockout_interval,
+            lockout_recovery,
+            in_lockout: tpma::Permanent::from_bits_truncate(permanent)
+                .contains(tpma::Permanent::IN_LOCKOUT),
+        })
+    }
+}
+
+/// The digests of all PCRs in a single PCR bank
+///
+/// Returned by [`TpmExt::read_all_pcrs`].
+#[cfg(feature = "alloc")]
+#[doc(cfg(feature = "alloc"))]
+#[derive(Clone, Debug)]
+pub struct PcrBank {
+    pub hash: tpmi::AlgHash,
+    /// Map of PCR index to PCR value
+    pub pcrs: BTreeMap<usize, tpmt::Hash>,
+}
+
+#[cfg(feature = "alloc")]
+const PROBE_RSA_KEY_BITS: [tpmi::RsaKeyBits; 4] = [1024, 2048, 3072, 4096];
+#[cfg(feature = "alloc")]
+const PROBE_ECC_CURVES: [tpm::EccCurve; 8] = [
+    tpm::EccCurve::NistP192,
+    tpm::EccCurve::NistP224,
+    tpm::EccCurve::NistP256,
+    tpm::EccCurve::NistP384,
+    tpm::EccCurve::NistP521,
+    tpm::EccCurve::BnP256,
+    tpm::EccCurve::BnP638,
+    tpm::EccCurve::Sm2P256,
+];
+#[cfg(feature = "alloc")]
+const PROBE_SYM_CIPHERS: [(tpmi::AlgSymObject, tpm::KeyBits); 7] = [
+    (tpm::Alg::Aes, 128),
+    (tpm::Alg::Aes, 192),
+    (tpm::Alg::Aes, 256),
+    (tpm::Alg::Sm4, 128),
+    (tpm::Alg::Camellia, 128),
+    (tpm::Alg::Camellia, 192),
+    (tpm::Alg::Camellia, 256),
+];
+#[cfg(feature = "alloc")]
+const PROBE_SYM_MODES: [tpmi::AlgSymMode; 5] = [
+    tpm::Alg::Ctr,
+    tpm::Alg::Ofb,
+    tpm::Alg::Cbc,
+    tpm::Alg::Cfb,
+    tpm::Alg::Ecb,
+];
+#[cfg(feature = "alloc")]
+const PROBE_HASHES: [tpmi::AlgHash; 8] = [
+    tpm::Alg::Sha1,
+    tpm::Alg::Sha256,
+    tpm::Alg::Sha384,
+    tpm::Alg::Sha512,
+    tpm::Alg::Sm3_256,
+    tpm::Alg::Sha3_256,
+    tpm::Alg::Sha3_384,
+    tpm::Alg::Sha3_512,
+];
+
+/// Algorithms and parameters supported by a TPM
+///
+/// Returned by [`TpmExt::algorithm_support`]. Only commonly used values are
+/// probed, so a TPM may support additional parameters not listed here.
+#[cfg(feature = "alloc")]
+#[doc(cfg(feature = "alloc"))]
+#[derive(Clone, Default, Debug)]
+pub struct AlgorithmSupport {
+    /// Supported RSA key sizes (with the default exponent and no scheme)
+    pub rsa_key_bits: Vec<tpmi::RsaKeyBits>,
+    /// Supported ECC curves (with no scheme or KDF)
+    pub ecc_curves: Vec<tpm::EccCurve>,
+    /// Supported symmetric cipher, key size and mode combinations
+    pub sym_ciphers: Vec<tpmt::SymDefObject>,
+    /// Hash algorithms supported for HMAC keys
+    pub hashes: Vec<tpmi::AlgHash>,
+}
+
+/// Dictionary attack state of a TPM
+///
+/// Returned by [`TpmExt::da_status`]. All times are in seconds.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DaStatus {
+    /// Number of authorization failures since the counter was last reset
+    pub lockout_counter: u32,
+    /// Number of failures before the TPM enters lockout
+    pub max_auth_fail: u32,
+    /// Time until the failure counter is decremented by one
+    pub lockout_interval: u32,
+    /// Time after a `lockoutAuth` failure before it may be used again
+    pub lockout_recovery: u32,
+    /// True if the TPM is currently in lockout
+    pub in_lockout: bool,
 }
 
 impl<T: TpmRun + ?Sized> TpmExt for T {}
+
+/// Reads a single TPM_PT property with TPM2_GetCapability.
+pub(crate) fn get_tpm_property<T: TpmRun + ?Sized>(
+    tpm: &mut T,
+    property: u32,
+) -> Result<u32, Error> {
+    let mut value = [0];
+    get_tpm_properties(tpm, property, &mut value)?;
+    Ok(value[0])
+}
+
+/// Reads the consecutive TPM_PT properties starting at `first` into `values`
+/// with TPM2_GetCapability. If the TPM returns fewer properties than requested
+/// (and sets moreData), the rest are read with further calls.
+fn get_tpm_properties<T: TpmRun + ?Sized>(
+    tpm: &mut T,
+    mut first: u32,
+    mut values: &mut [u32],
+) -> Result<(), Error> {
+    while !values.is_empty() {
+        let rsp = tpm.run(GetCapability {
+            capability: tpm::Cap::TpmProperties,
+            property: first,
+            property_count: values.len().try_into().map_err(MarshalError::from)?,
+        })?;
+        let tpms::CapabilityData::TpmProperties(props) = rsp.capability_data else {
+            return Err(UnmarshalError::InvalidValue.into());
+        };
+        let mut read = 0;
+        for prop in props {
+            match values.get_mut(read) {
+                Some(value) if prop.property == first.wrapping_add(read as u32) => {
+                    *value = prop.value
+                }
+                _ => return Err(UnmarshalError::InvalidValue.into()),
+            }
+            read += 1;
+        }
+        if read == 0 || (read < values.len() && !rsp.more_data) {
+            return Err(UnmarshalError::InvalidValue.into());
+        }
+        values = &mut core::mem::take(&mut values)[read..];
+        first = first.wrapping_add(read as u32);
+    }
+    Ok(())
+}
+
+/// Returns the maximum size of a single NV read or write.
+fn nv_buffer_max<T: TpmRun + ?Sized>(tpm: &mut T) -> Result<u16, Error> {
+    let max = get_tpm_property(tpm, tpm::pt::NV_BUFFER_MAX)?;
+    match max.try_into().unwrap_or(u16::MAX) {
+        0 => Err(UnmarshalError::InvalidValue.into()),
+        max => Ok(max),
+    }
+}
+
+/// Computes a new PCR allocation from the `current` one.
+fn reallocate<'a>(
+    current: impl Iterator<Item = tpms::PcrSelection>,
+    enable: &[tpmi::AlgHash],
+    disable: &[tpmi::AlgHash],
+    banks: &'a mut [tpms::PcrSelection],
+) -> Result<&'a [tpms::PcrSelection], Error> {
+    fn push(
+        banks: &mut [tpms::PcrSelection],
+        len: &mut usize,
+        sel: tpms::PcrSelection,
+    ) -> Result<(), MarshalError> {
+        *banks.get_mut(*len).ok_or(MarshalError::BufferOverflow)? = sel;
+        *len += 1;
+        Ok(())
+    }
+
+    let mut len = 0;
+    for sel in current {
+        push(banks, &mut len, sel)?;
+    }
+    // Enabled banks select the same PCRs as the other allocated banks.
+    let all = (banks[..len].iter()).fold(tpms::PcrSelect::range(0..tpms::MIN_PCRS), |all, sel| {
+        all.union(&sel.select)
+    });
+    for sel in &mut banks[..len] {
+        if disable.contains(&sel.hash) {
+            sel.select = tpms::PcrSelect::new();
+        } else if enable.contains(&sel.hash) {
+            sel.select = all;
+        }
+    }
+    // Unimplemented banks are not reported by the TPM, but we still request
+    // them so that the TPM returns the appropriate error.
+    for &hash in enable {
+        if !disable.contains(&hash) && banks[..len].iter().all(|s| s.hash != hash) {
+            push(banks, &mut len, tpms::PcrSelection { hash, select: all })?;
+        }
+    }
+    Ok(&banks[..len])
+}
+
+/// Stores the digests from `rsp` in `banks`, removing them from `remaining`.
+///
+/// Returns false if no digests were returned.
+#[cfg(feature = "alloc")]
+fn record_pcrs(
+    rsp: PcrReadResponse,
+    remaining: &mut [tpms::PcrSelection],
+    banks: &mut [PcrBank],
+) -> Result<bool, Error> {
+    let mut digests = rsp.pcr_values;
+    let mut progress = false;
+    for sel in rsp.pcr_selection {
+        let i = (banks.iter())
+            .position(|bank| bank.hash == sel.hash)
+            .ok_or(UnmarshalError::InvalidValue)?;
+        for pcr in sel.select.iter() {
+            let mut digest = digests.next().ok_or(UnmarshalError::BufferOverflow)?;
+            let hash = tpmt::Hash::unmarshal_with_alg(sel.hash, &mut digest)?;
+            if !digest.is_empty() {
+                return Err(UnmarshalError::BufferRemaining.into());
+            }
+            banks[i].pcrs.insert(pcr, hash);
+            remaining[i].select.remove(pcr);
+            progress = true;
+        }
+    }
+    Ok(progress)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::vec;
+
+    use super::*;
+    use crate::{test::ScriptTpm, types::tpml, Marshal, Unmarshal};
+
+    /// Parameters of a GetCapability response for `capability`
+    pub(crate) fn capability(capability: tpm::Cap, data: &[u8]) -> Vec<u8> {
+        let mut params = vec![0];
+        params.extend_from_slice(&(capability as u32).to_be_bytes());
+        params.extend_from_slice(data);
+        params
+    }
+
+    /// Parameters of a GetCapability response with a single TPM property
+    fn property(property: u32, value: u32) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 1];
+        data.extend_from_slice(&property.to_be_bytes());
+        data.extend_from_slice(&value.to_be_bytes());
+        capability(tpm::Cap::TpmProperties, &data)
+    }
+
+    /// Parameters of an NvReadPublic response
+    pub(crate) fn nv_public(attributes: tpma::Nv, data_size: u16) -> Vec<u8> {
+        let public = tpms::NvPublic {
+            nv_index: 0x01000000,
+            name_alg: tpm::Alg::Sha256,
+            attributes,
+            auth_policy: &[],
+            data_size,
+        };
+        let mut buf = [0; 64];
+        let mut rest = &mut buf[..];
+        public.marshal(&mut rest).unwrap();
+        let len = 64 - rest.len();
+        let mut params = (len as u16).to_be_bytes().to_vec();
+        params.extend_from_slice(&buf[..len]);
+        // The Name is only checked to be well-formed.
+        params.extend_from_slice(&[0, 4, 0x01, 0, 0, 0]);
+        params
+    }
+
+    fn read_u16(buf: &[u8], offset: usize) -> u16 {
+        u16::from_be_bytes([buf[offset], buf[offset + 1]])
+    }
+
+    #[test]
+    fn nv_chunks() {
+        let auth = crate::types::PasswordAuth(&[]);
+        let auth_handle = AuthHandle {
+            handle: 0x01000000,
+            auth: &auth,
+        };
+        let data: Vec<u8> = (0..10).collect();
+        // (index size, expected (offset, size) of each read or write)
+        let cases: [(u16, &[(u16, u16)]); 3] = [
+            (8, &[(0, 4), (4, 4)]),
+            (10, &[(0, 4), (4, 4), (8, 2)]),
+            (0, &[]),
+        ];
+        for (size, chunks) in cases {
+            let mut ops = Vec::new();
+            let mut tpm = ScriptTpm::new(|code, params: &[u8]| match code {
+                tpm::CC::GetCapability => (0, property(tpm::pt::NV_BUFFER_MAX, 4)),
+                tpm::CC::NvReadPublic => (0, nv_public(tpma::Nv::empty(), size)),
+                tpm::CC::NvRead => {
+                    let (len, offset) = (read_u16(params, 0), read_u16(params, 2));
+                    ops.push((offset, len));
+                    let mut params = len.to_be_bytes().to_vec();
+                    params.extend_from_slice(&data[offset.into()..(offset + len).into()]);
+                    (0, params)
+                }
+                tpm::CC::NvWrite => {
+                    let len = read_u16(params, 0);
+                    let offset = read_u16(params, 2 + usize::from(len));
+                    assert_eq!(
+                        params[2..][..len.into()],
+                        data[offset.into()..][..len.into()]
+                    );
+                    ops.push((offset, len));
+                    (0, Vec::new())
+                }
+                _ => (tpm::rc::COMMAND_CODE, Vec::new()),
+            });
+            let read = tpm.nv_read_all(auth_handle, 0x01000000).unwrap();
+            assert_eq!(read, data[..size.into()]);
+            tpm.nv_write_all(auth_handle, 0x01000000, &data[..size.into()])
+                .unwrap();
+            drop(tpm);
+            assert_eq!(ops[..chunks.len()], *chunks, "reads of {size} bytes");
+            assert_eq!(ops[chunks.len()..], *chunks, "writes of {size} bytes");
+        }
+    }
+
+    #[test]
+    fn poll_self_tests() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        static SLEPT_MS: AtomicU32 = AtomicU32::new(0);
+        fn sleep(d: Duration) {
+            SLEPT_MS.fetch_add(d.as_millis() as u32, Ordering::Relaxed);
+        }
+
+        // (algorithms still to test, results of GetTestResult)
+        let run = |to_do: &[u8], results: &[u32]| {
+            SLEPT_MS.store(0, Ordering::Relaxed);
+            let mut results = results.iter();
+            let mut tpm = ScriptTpm::new(|code, _: &[u8]| match code {
+                tpm::CC::IncrementalSelfTest => {
+                    let mut params = (to_do.len() as u32 / 2).to_be_bytes().to_vec();
+                    params.extend_from_slice(to_do);
+                    (0, params)
+                }
+                tpm::CC::GetTestResult => {
+                    let rc = results.next().copied().unwrap_or(tpm::rc::TESTING);
+                    let mut params = vec![0, 0];
+                    params.extend_from_slice(&rc.to_be_bytes());
+                    (0, params)
+                }
+                _ => (tpm::rc::COMMAND_CODE, Vec::new()),
+            });
+            let result = tpm.ensure_tested(&[tpm::Alg::Sha256], sleep);
+            (result, tpm.commands.len(), SLEPT_MS.load(Ordering::Relaxed))
+        };
+
+        // Nothing left to test, so there is no need to poll
+        let (result, commands, slept) = run(&[], &[]);
+        assert!(result.is_ok());
+        assert_eq!((commands, slept), (1, 0));
+
+        let (result, commands, slept) = run(&[0x00, 0x0B], &[tpm::rc::TESTING, 0]);
+        assert!(result.is_ok());
+        assert_eq!((commands, slept), (3, 10));
+
+        let (result, commands, _) = run(&[0x00, 0x0B], &[tpm::rc::FAILURE]);
+        assert_eq!(
+            result.unwrap_err().tpm_error().unwrap().get(),
+            tpm::rc::FAILURE
+        );
+        assert_eq!(commands, 2);
+
+        // Testing never finishes
+        let (result, commands, slept) = run(&[0x00, 0x0B], &[]);
+        assert_eq!(
+            result.unwrap_err().tpm_error().unwrap().get(),
+            tpm::rc::TESTING
+        );
+        assert_eq!(commands, 1 + MAX_TEST_POLLS as usize);
+        assert_eq!(slept, 13270);
+    }
+
+    #[test]
+    fn supports_only_rejects_parameters() {
+        let mut tpm = ScriptTpm::new(|_, _: &[u8]| (0, Vec::new()));
+        let parms = tpmt::PublicParms::Rsa(tpms::RsaParms {
+            key_bits: 1024,
+            ..Default::default()
+        });
+        assert!(tpm.supports(&parms).unwrap());
+
+        // TPM_RC_KEY_SIZE for parameter 1
+        let mut tpm =
+            ScriptTpm::new(|_, _: &[u8]| (tpm::rc::KEY_SIZE | tpm::rc::P | 0x100, Vec::new()));
+        assert!(!tpm.supports(&parms).unwrap());
+
+        for rc in [tpm::rc::RETRY, tpm::rc::TESTING, tpm::rc::FAILURE] {
+            let mut tpm = ScriptTpm::new(|_, _: &[u8]| (rc, Vec::new()));
+            let e = tpm.supports(&parms).unwrap_err();
+            assert_eq!(e.tpm_error().unwrap().get(), rc);
+        }
+    }
+
+    #[test]
+    fn da_status_snapshot() {
+        let mut tpm = ScriptTpm::new(|code, params: &[u8]| {
+            assert_eq!(code, tpm::CC::GetCapability);
+            let first = u32::from_be_bytes(params[4..8].try_into().unwrap());
+            let count = u32::from_be_bytes(params[8..12].try_into().unwrap());
+            let mut data = count.to_be_bytes().to_vec();
+            for property in first..first + count {
+                let value = match property {
+                    tpm::pt::PERMANENT => tpma::Permanent::IN_LOCKOUT.bits(),
+                    _ => property - tpm::pt::LOCKOUT_COUNTER,
+                };
+                data.extend_from_slice(&property.to_be_bytes());
+                data.extend_from_slice(&value.to_be_bytes());
+            }
+            (0, capability(tpm::Cap::TpmProperties, &data))
+        });
+        let status = tpm.da_status().unwrap();
+        assert_eq!(tpm.commands.len(), 2);
+        assert_eq!(status.lockout_counter, 0);
+        assert_eq!(status.max_auth_fail, 1);
+        assert_eq!(status.lockout_interval, 2);
+        assert_eq!(status.lockout_recovery, 3);
+        assert!(status.in_lockout);
+    }
+
+    #[test]
+    fn split_tpm_properties() {
+        // Returns at most 2 of the requested properties, setting moreData if
+        // `more` and some were left out.
+        let respond = |more: bool| {
+            move |_, params: &[u8]| {
+                let first = u32::from_be_bytes(params[4..8].try_into().unwrap());
+                let count = u32::from_be_bytes(params[8..12].try_into().unwrap());
+                let returned = count.min(2);
+                let mut data = returned.to_be_bytes().to_vec();
+                for property in first..first + returned {
+                    data.extend_from_slice(&property.to_be_bytes());
+                    data.extend_from_slice(&(property - tpm::pt::LOCKOUT_COUNTER).to_be_bytes());
+                }
+                let mut params = capability(tpm::Cap::TpmProperties, &data);
+                params[0] = (more && returned < count).into();
+                (0, params)
+            }
+        };
+
+        let mut tpm = ScriptTpm::new(respond(true));
+        let mut values = [0; 5];
+        get_tpm_properties(&mut tpm, tpm::pt::LOCKOUT_COUNTER, &mut values).unwrap();
+        assert_eq!(values, [0, 1, 2, 3, 4]);
+        assert_eq!(tpm.commands.len(), 3);
+
+        let mut tpm = ScriptTpm::new(respond(false));
+        let e = get_tpm_properties(&mut tpm, tpm::pt::LOCKOUT_COUNTER, &mut values).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            ErrorKind::Unmarshal(UnmarshalError::InvalidValue)
+        ));
+    }
+
+    #[test]
+    fn read_changing_pcrs() {
+        let mut counter = 0u32;
+        let mut tpm = ScriptTpm::new(|code, _: &[u8]| match code {
+            tpm::CC::GetCapability => (
+                0,
+                capability(tpm::Cap::Pcrs, &[0, 0, 0, 1, 0x00, 0x04, 3, 0x03, 0, 0]),
+            ),
+            tpm::CC::PcrRead => {
+                // Each read returns one digest, but the counter always changes.
+                counter += 1;
+                let mut params = counter.to_be_bytes().to_vec();
+                params.extend_from_slice(&[0, 0, 0, 1, 0x00, 0x04, 3, 0x01, 0, 0]);
+                params.extend_from_slice(&[0, 0, 0, 1, 0, 20]);
+                params.extend_from_slice(&[0; 20]);
+                (0, params)
+            }
+            _ => (tpm::rc::COMMAND_CODE, Vec::new()),
+        });
+        let e = tpm.read_all_pcrs().unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::PcrsChanged));
+        assert_eq!(tpm.commands.len(), 3 * MAX_PCR_READS);
+    }
+
+    #[test]
+    fn reallocate_banks() {
+        #[rustfmt::skip]
+        let mut buf: &[u8] = &[
+            0, 0, 0, 2,
+            0x00, 0x04, 4, 0xFF, 0xFF, 0xFF, 0x01,
+            0x00, 0x0B, 3, 0x00, 0x00, 0x00,
+        ];
+        let current = tpml::PcrSelectionOut::unmarshal_val(&mut buf).unwrap();
+
+        let mut banks = [tpms::PcrSelection::default(); MAX_BANKS];
+        let enable = [tpm::Alg::Sha256, tpm::Alg::Sha384, tpm::Alg::Sha384];
+        let disable = [tpm::Alg::Sha1];
+        let banks = reallocate(current, &enable, &disable, &mut banks).unwrap();
+
+        assert_eq!(banks.len(), 3);
+        assert_eq!(banks[0].hash, tpm::Alg::Sha1);
+        assert!(banks[0].select.is_empty());
+        assert_eq!(banks[1].hash, tpm::Alg::Sha256);
+        assert_eq!(banks[1].select, tpms::PcrSelect::range(0..25));
+        assert_eq!(banks[2].hash, tpm::Alg::Sha384);
+        assert_eq!(banks[2].select, tpms::PcrSelect::range(0..25));
+    }
+
+    /// Runs [`TpmExt::pcr_allocate`] against a TPM simulator listening for
+    /// raw commands at the address in `TPM2_SIMULATOR`, such as `swtpm socket
+    /// --tpm2 --server type=tcp,port=2321 --ctrl type=tcp,port=2322 --flags
+    /// not-need-init` with `TPM2_SIMULATOR=127.0.0.1:2321`.
+    #[cfg(feature = "std")]
+    #[test]
+    #[ignore = "requires a TPM simulator, see TPM2_SIMULATOR"]
+    fn simulator_pcr_allocate() {
+        use crate::{commands::Startup, os::RwTpm, types::PasswordAuth};
+
+        let addr = std::env::var("TPM2_SIMULATOR").expect("TPM2_SIMULATOR is not set");
+        let mut tpm = RwTpm::framed(std::net::TcpStream::connect(addr).unwrap());
+        match tpm.run(Startup {
+            startup_type: tpm::SU::Clear,
+        }) {
+            Err(e) if e.tpm_error().map(|e| e.get()) != Some(tpm::rc::INITIALIZE) => panic!("{e}"),
+            _ => {}
+        }
+
+        let platform_auth = PasswordAuth(&[]);
+        let rsp = tpm
+            .pcr_allocate(&platform_auth, &[tpm::Alg::Sha384], &[tpm::Alg::Sha1])
+            .unwrap();
+        assert!(rsp.allocation_success);
+        assert!(rsp.max_pcr >= 24);
+        assert!(rsp.size_needed <= rsp.size_available);
+
+        // Replace the pending allocation with the current one.
+        let rsp = tpm.pcr_allocate(&platform_auth, &[], &[]).unwrap();
+        assert!(rsp.allocation_success);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn record_partial_read() {
+        let mut remaining = [tpms::PcrSelection {
+            hash: tpm::Alg::Sha1,
+            select: tpms::PcrSelect::range(0..tpms::MIN_PCRS),
+        }];
+        let mut banks = [PcrBank {
+            hash: tpm::Alg::Sha1,
+            pcrs: BTreeMap::new(),
+        }];
+
+        #[rustfmt::skip]
+        let mut buf: &[u8] = &[
+            0, 0, 0, 7,
+            0, 0, 0, 1, 0x00, 0x04, 3, 0x05, 0x00, 0x00,
+            0, 0, 0, 2,
+            0, 20, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+            0, 20, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+        ];
+        let mut rsp = PcrReadResponse::default();
+        crate::marshal::ResponseData::unmarshal_params(&mut rsp, &mut buf).unwrap();
+        assert!(record_pcrs(rsp, &mut remaining, &mut banks).unwrap());
+
+        let pcrs: Vec<_> = banks[0]
+            .pcrs
+            .iter()
+            .map(|(&i, h)| (i, h.digest()[0]))
+            .collect();
+        assert_eq!(pcrs, [(0, 1), (2, 2)]);
+        let read: tpms::PcrSelect = [0, 2].into_iter().collect();
+        assert_eq!(
+            remaining[0].select,
+            tpms::PcrSelect::range(0..24).difference(&read)
+        );
+    }
+}
@@ -9,19 +9,29 @@ extern crate alloc;
 extern crate std;
 
 mod ext;
+#[macro_use]
 mod marshal;
 mod polyfill;
 mod run;
+#[cfg(feature = "tokio")]
+mod run_async;
 
 pub mod commands;
+pub mod ecdaa;
 pub mod error;
+pub mod nv;
 pub mod os;
 pub mod types;
+pub mod wrap;
 
 pub use error::Error;
-pub use ext::TpmExt;
-pub use marshal::{Marshal, MarshalFixed, Unmarshal, UnmarshalFixed};
+#[cfg(feature = "alloc")]
+pub use ext::{AlgorithmSupport, PcrBank};
+pub use ext::{DaStatus, TpmExt};
+pub use marshal::{CommandData, Marshal, MarshalFixed, ResponseData, Unmarshal, UnmarshalFixed};
 pub use run::{Auths, Command, Tpm, TpmRun, WithAuth};
+#[cfg(feature = "tokio")]
+pub use run_async::{AsyncTpm, AsyncTpmRun};
 
 #[cfg(test)]
 mod test {
@@ -29,10 +39,134 @@ mod test {
 
     use crate::{
         commands::*,
+        error::DriverError,
+        polyfill::ToUsize,
         types::{tpm, Auth, PasswordAuth},
         *,
     };
 
+    /// Fake TPM shared by the tests, answering each command with a scripted
+    /// response. `commands` records the code of each command it was sent.
+    pub(crate) struct ScriptTpm<R> {
+        respond: R,
+        pub(crate) commands: Vec<tpm::CC>,
+        pub(crate) cmd: [u8; 1024],
+        pub(crate) rsp: Vec<u8>,
+    }
+
+    /// How a [`ScriptTpm`] answers a command
+    pub(crate) trait Respond {
+        fn respond(&mut self, code: tpm::CC, cmd: &[u8], rsp: &mut Vec<u8>);
+    }
+
+    /// Answers with `respond(code, params)`, see [`ScriptTpm::new`].
+    pub(crate) struct Script<F>(F);
+
+    /// Answers by calling `respond(cmd, rsp)`, see [`ScriptTpm::raw`].
+    pub(crate) struct Raw<F>(F);
+
+    impl<R> ScriptTpm<R> {
+        fn with(respond: R) -> Self {
+            Self {
+                respond,
+                commands: Vec::new(),
+                cmd: [0; 1024],
+                rsp: Vec::new(),
+            }
+        }
+    }
+
+    impl<F: FnMut(tpm::CC, &[u8]) -> (u32, Vec<u8>)> ScriptTpm<Script<F>> {
+        /// Answers each command with `respond(code, params)`, which returns
+        /// the response code and parameters. Each session in a command is
+        /// answered with an empty password session.
+        pub(crate) fn new(respond: F) -> Self {
+            Self::with(Script(respond))
+        }
+    }
+
+    impl<F: FnMut(&[u8], &mut Vec<u8>)> ScriptTpm<Raw<F>> {
+        /// Answers each command by calling `respond(cmd, rsp)`, where `rsp`
+        /// still holds the previous response (so can be left unchanged).
+        pub(crate) fn raw(respond: F) -> Self {
+            Self::with(Raw(respond))
+        }
+    }
+
+    impl<R: Respond> Tpm for ScriptTpm<R> {
+        fn command_buf(&mut self) -> &mut [u8] {
+            &mut self.cmd
+        }
+        fn response_buf(&self) -> &[u8] {
+            &self.rsp
+        }
+        fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
+            let cmd = &self.cmd[..cmd_size.to_usize()];
+            let code = tpm::CC::new(u32::from_be_bytes(cmd[6..10].try_into().unwrap()));
+            self.commands.push(code);
+            self.respond.respond(code, cmd, &mut self.rsp);
+            Ok(())
+        }
+    }
+
+    impl<F: FnMut(&[u8], &mut Vec<u8>)> Respond for Raw<F> {
+        fn respond(&mut self, _: tpm::CC, cmd: &[u8], rsp: &mut Vec<u8>) {
+            (self.0)(cmd, rsp)
+        }
+    }
+
+    fn take<'a>(buf: &mut &'a [u8], n: usize) -> &'a [u8] {
+        let (head, tail) = buf.split_at(n);
+        *buf = tail;
+        head
+    }
+
+    fn take_u16(buf: &mut &[u8]) -> usize {
+        u16::from_be_bytes(take(buf, 2).try_into().unwrap()).into()
+    }
+
+    fn take_u32(buf: &mut &[u8]) -> usize {
+        u32::from_be_bytes(take(buf, 4).try_into().unwrap()).to_usize()
+    }
+
+    impl<F: FnMut(tpm::CC, &[u8]) -> (u32, Vec<u8>)> Respond for Script<F> {
+        fn respond(&mut self, code: tpm::CC, mut cmd: &[u8], rsp: &mut Vec<u8>) {
+            let tag = take_u16(&mut cmd);
+            take(&mut cmd, 8 + 4 * code.attributes().unwrap().c_handles());
+            let mut sessions = 0;
+            if tag == tpm::ST::Sessions as usize {
+                let size = take_u32(&mut cmd);
+                let mut auths = take(&mut cmd, size);
+                while !auths.is_empty() {
+                    take(&mut auths, 4);
+                    let nonce = take_u16(&mut auths);
+                    take(&mut auths, nonce + 1);
+                    let hmac = take_u16(&mut auths);
+                    take(&mut auths, hmac);
+                    sessions += 1;
+                }
+            }
+            let (rc, params) = (self.0)(code, cmd);
+
+            let (tag, mut body) = match (rc, sessions) {
+                (0, 0) => (tpm::ST::NoSessions, params),
+                (0, _) => {
+                    let mut body = (params.len() as u32).to_be_bytes().to_vec();
+                    body.extend_from_slice(&params);
+                    for _ in 0..sessions {
+                        body.extend_from_slice(&[0, 0, 1, 0, 0]);
+                    }
+                    (tpm::ST::Sessions, body)
+                }
+                _ => (tpm::ST::NoSessions, Vec::new()),
+            };
+            *rsp = (tag as u16).to_be_bytes().to_vec();
+            rsp.extend_from_slice(&(10 + body.len() as u32).to_be_bytes());
+            rsp.extend_from_slice(&rc.to_be_bytes());
+            rsp.append(&mut body);
+        }
+    }
+
     #[test]
     fn can_exec() {
         #[allow(dead_code)]
@@ -6,10 +6,30 @@ use crate::error::{MarshalError, UnmarshalError};
 
 pub trait Marshal {
     fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError>;
+    /// Like [`marshal`](Self::marshal), but on failure records the innermost
+    /// structure field being marshalled.
+    #[doc(hidden)]
+    fn marshal_traced(
+        &self,
+        buf: &mut &mut [u8],
+        _: &mut Option<&'static str>,
+    ) -> Result<(), MarshalError> {
+        self.marshal(buf)
+    }
 }
 
 pub trait Unmarshal<'t> {
     fn unmarshal(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError>;
+    /// Like [`unmarshal`](Self::unmarshal), but on failure records the
+    /// innermost structure field being unmarshalled.
+    #[doc(hidden)]
+    fn unmarshal_traced(
+        &mut self,
+        buf: &mut &'t [u8],
+        _: &mut Option<&'static str>,
+    ) -> Result<(), UnmarshalError> {
+        self.unmarshal(buf)
+    }
     fn unmarshal_val(buf: &mut &'t [u8]) -> Result<Self, UnmarshalError>
     where
         Self: Default,
@@ -31,30 +51,137 @@ pub trait UnmarshalFixed: MarshalFixed {
     fn unmarshal_fixed(arr: &Self::ARRAY) -> Self;
 }
 
-mod sealed {
-    use super::*;
-    /// The object-safe supertrait of [`Command`]
-    pub trait CommandData {
-        fn marshal_handles(&self, _: &mut &mut [u8]) -> Result<(), MarshalError> {
-            Ok(())
+/// The object-safe supertrait of [`Command`](crate::Command)
+///
+/// Implement this (along with [`Command`](crate::Command)) to run commands
+/// this crate doesn't define, such as vendor-specific commands.
+pub trait CommandData {
+    fn marshal_handles(&self, _: &mut &mut [u8]) -> Result<(), MarshalError> {
+        Ok(())
+    }
+    fn marshal_params(&self, _: &mut &mut [u8]) -> Result<(), MarshalError> {
+        Ok(())
+    }
+    #[doc(hidden)]
+    fn marshal_handles_traced(
+        &self,
+        buf: &mut &mut [u8],
+        _: &mut Option<&'static str>,
+    ) -> Result<(), MarshalError> {
+        self.marshal_handles(buf)
+    }
+    #[doc(hidden)]
+    fn marshal_params_traced(
+        &self,
+        buf: &mut &mut [u8],
+        _: &mut Option<&'static str>,
+    ) -> Result<(), MarshalError> {
+        self.marshal_params(buf)
+    }
+}
+
+/// The object-safe supertrait of [`Response`](crate::Command::Response)
+pub trait ResponseData<'t> {
+    fn unmarshal_handles(&mut self, _: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        Ok(())
+    }
+    fn unmarshal_params(&mut self, _: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        Ok(())
+    }
+    #[doc(hidden)]
+    fn unmarshal_handles_traced(
+        &mut self,
+        buf: &mut &'t [u8],
+        _: &mut Option<&'static str>,
+    ) -> Result<(), UnmarshalError> {
+        self.unmarshal_handles(buf)
+    }
+    #[doc(hidden)]
+    fn unmarshal_params_traced(
+        &mut self,
+        buf: &mut &'t [u8],
+        _: &mut Option<&'static str>,
+    ) -> Result<(), UnmarshalError> {
+        self.unmarshal_params(buf)
+    }
+}
+impl ResponseData<'_> for () {}
+
+/// Implements [`Marshal`] for a structure by marshalling the listed fields in
+/// order. With `handles:` or `params:`, implements that part of
+/// [`CommandData`] instead.
+///
+/// On failure, the innermost structure and field which failed is recorded
+/// (e.g. `"tpms::Context.context_blob"`) and reported in the error's
+/// [`Context`](crate::error::Context).
+macro_rules! marshal_fields {
+    (@impl $method:ident, $traced:ident, $name:expr, $($f:ident $(. $ff:ident)*),+) => {
+        fn $method(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+            self.$traced(buf, &mut None)
         }
-        fn marshal_params(&self, _: &mut &mut [u8]) -> Result<(), MarshalError> {
+        fn $traced(
+            &self,
+            buf: &mut &mut [u8],
+            field: &mut Option<&'static str>,
+        ) -> Result<(), MarshalError> {
+            $(self.$f $(.$ff)*.marshal_traced(buf, field).map_err(|e| {
+                field.get_or_insert(concat!($name, ".", stringify!($f)));
+                e
+            })?;)+
             Ok(())
         }
-    }
+    };
+    (@with $method:ident, $traced:ident, $s:ident $(:: $ss:ident)* {
+        $($f:ident $(. $ff:ident)*),+ $(,)?
+    }) => {
+        marshal_fields!(
+            @impl $method, $traced, concat!(stringify!($s) $(, "::", stringify!($ss))*),
+            $($f $(. $ff)*),+
+        );
+    };
+    (handles: $($t:tt)+) => { marshal_fields!(@with marshal_handles, marshal_handles_traced, $($t)+); };
+    (params: $($t:tt)+) => { marshal_fields!(@with marshal_params, marshal_params_traced, $($t)+); };
+    ($($t:tt)+) => { marshal_fields!(@with marshal, marshal_traced, $($t)+); };
+}
 
-    /// The object-safe supertrait of [`Response`](Command::Response)
-    pub trait ResponseData<'t> {
-        fn unmarshal_handles(&mut self, _: &mut &'t [u8]) -> Result<(), UnmarshalError> {
-            Ok(())
+/// Implements [`Unmarshal<'t>`](Unmarshal) for a structure by unmarshalling
+/// the listed fields in order. With `handles:` or `params:`, implements that
+/// part of [`ResponseData<'t>`](ResponseData) instead.
+///
+/// Failures are recorded as with `marshal_fields!`.
+macro_rules! unmarshal_fields {
+    (@impl $method:ident, $traced:ident, $t:lifetime, $name:expr, $($f:ident),+) => {
+        fn $method(&mut self, buf: &mut &$t [u8]) -> Result<(), UnmarshalError> {
+            self.$traced(buf, &mut None)
         }
-        fn unmarshal_params(&mut self, _: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        fn $traced(
+            &mut self,
+            buf: &mut &$t [u8],
+            field: &mut Option<&'static str>,
+        ) -> Result<(), UnmarshalError> {
+            $(self.$f.unmarshal_traced(buf, field).map_err(|e| {
+                field.get_or_insert(concat!($name, ".", stringify!($f)));
+                e
+            })?;)+
             Ok(())
         }
-    }
-    impl ResponseData<'_> for () {}
+    };
+    (@with $method:ident, $traced:ident, $t:lifetime, $s:ident $(:: $ss:ident)* {
+        $($f:ident),+ $(,)?
+    }) => {
+        unmarshal_fields!(
+            @impl $method, $traced, $t, concat!(stringify!($s) $(, "::", stringify!($ss))*),
+            $($f),+
+        );
+    };
+    ($t:lifetime, handles: $($r:tt)+) => {
+        unmarshal_fields!(@with unmarshal_handles, unmarshal_handles_traced, $t, $($r)+);
+    };
+    ($t:lifetime, params: $($r:tt)+) => {
+        unmarshal_fields!(@with unmarshal_params, unmarshal_params_traced, $t, $($r)+);
+    };
+    ($t:lifetime, $($r:tt)+) => { unmarshal_fields!(@with unmarshal, unmarshal_traced, $t, $($r)+); };
 }
-pub(crate) use sealed::*;
 
 #[inline]
 pub(crate) fn pop_array<'a, const N: usize>(
@@ -71,10 +198,10 @@ pub(crate) fn pop_array<'a, const N: usize>(
 pub(crate) fn pop_array_mut<'a, const N: usize>(
     buf: &mut &'a mut [u8],
 ) -> Result<&'a mut [u8; N], MarshalError> {
-    let old = mem::take(buf);
-    let (arr, suffix) = old
-        .split_first_chunk_mut()
-        .ok_or(MarshalError::BufferOverflow)?;
+    if buf.len() < N {
+        return Err(MarshalError::BufferOverflow);
+    }
+    let (arr, suffix) = mem::take(buf).split_first_chunk_mut().unwrap();
     *buf = suffix;
     Ok(arr)
 }
@@ -104,6 +231,17 @@ pub(crate) fn pop_slice_mut<'a>(
     Ok(prefix)
 }
 
+/// Reads the `u16` at `offset` in a marshalled buffer, if it is in bounds.
+#[cfg(feature = "alloc")]
+pub(crate) fn read_u16(buf: &[u8], offset: usize) -> Option<u16> {
+    u16::unmarshal_val(&mut buf.get(offset..)?).ok()
+}
+
+/// Reads the `u32` at `offset` in a marshalled buffer, if it is in bounds.
+pub(crate) fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    u32::unmarshal_val(&mut buf.get(offset..)?).ok()
+}
+
 impl<const N: usize, T: MarshalFixed<ARRAY = [u8; N]>> Marshal for T {
     fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
         let arr = pop_array_mut(buf)?;
@@ -0,0 +1,399 @@
+//! Typed wrappers for special NV Indices
+//!
+//! Ordinary NV Indices hold arbitrary data and are accessed with
+//! [`TpmExt::nv_read_all`](crate::TpmExt::nv_read_all) and
+//! [`TpmExt::nv_write_all`](crate::TpmExt::nv_write_all). The other index
+//! types can only be modified in specific ways:
+//!   - [`NvCounter`]: a 64-bit counter that can only be incremented
+//!   - [`NvBitField`]: 64 bits that can only be set, never cleared
+//!   - [`NvExtendIndex`]: a digest that can only be extended, like a PCR
+//!
+//! Each wrapper is either created with `define` (which defines a new index of
+//! the correct type and size) or with `open` (which checks the type of an
+//! existing index).
+
+use crate::{
+    commands::{NvDefineSpace, NvExtend, NvIncrement, NvRead, NvReadPublic, NvSetBits},
+    error::{NvTypeError, UnmarshalError},
+    types::{tpm, tpmi, tpms, tpmt, AuthHandle},
+    Error, TpmRun, UnmarshalFixed,
+};
+
+/// Defines a new NV Index of type `nt`, returning its handle.
+fn define<T: TpmRun + ?Sized>(
+    tpm: &mut T,
+    auth_handle: AuthHandle,
+    auth: &[u8],
+    mut public: tpms::NvPublic,
+    nt: tpm::NT,
+    data_size: u16,
+) -> Result<tpmi::RhNvIndex, Error> {
+    public.attributes = public.attributes.with_nt(nt);
+    public.data_size = data_size;
+    tpm.run(NvDefineSpace {
+        auth_handle,
+        auth,
+        public_info: &public,
+    })?;
+    Ok(public.nv_index)
+}
+
+/// Checks that `nv_index` has type `nt`, returning its name algorithm.
+fn open<T: TpmRun + ?Sized>(
+    tpm: &mut T,
+    nv_index: tpmi::RhNvIndex,
+    nt: tpm::NT,
+) -> Result<tpmi::AlgHash, Error> {
+    let public = tpm.run(NvReadPublic { nv_index })?.nv_public.0;
+    let actual = public.attributes.nt();
+    if actual != Some(nt) {
+        return Err(NvTypeError {
+            nv_index,
+            expected: nt,
+            actual,
+        }
+        .into());
+    }
+    Ok(public.name_alg)
+}
+
+/// Reads the 8-byte value of a counter or bit field index.
+fn read_u64<T: TpmRun + ?Sized>(
+    tpm: &mut T,
+    auth_handle: AuthHandle,
+    nv_index: tpmi::RhNvIndex,
+) -> Result<u64, Error> {
+    let rsp = tpm.run(NvRead {
+        auth_handle,
+        nv_index,
+        size: 8,
+        offset: 0,
+    })?;
+    let arr = rsp
+        .data
+        .try_into()
+        .map_err(|_| UnmarshalError::InvalidValue)?;
+    Ok(u64::unmarshal_fixed(arr))
+}
+
+/// An NV Index of type [`tpm::NT::Counter`]
+///
+/// The counter can only be incremented, and its value never decreases (even
+/// if the index is deleted and redefined), making it suitable for
+/// anti-rollback protection.
+#[derive(Clone, Copy, Debug)]
+pub struct NvCounter {
+    nv_index: tpmi::RhNvIndex,
+}
+
+impl NvCounter {
+    /// Defines a new counter. The type and size in `public` are ignored.
+    pub fn define<T: TpmRun + ?Sized>(
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+        auth: &[u8],
+        public: tpms::NvPublic,
+    ) -> Result<Self, Error> {
+        let nv_index = define(tpm, auth_handle, auth, public, tpm::NT::Counter, 8)?;
+        Ok(Self { nv_index })
+    }
+
+    /// Opens an existing counter.
+    pub fn open<T: TpmRun + ?Sized>(tpm: &mut T, nv_index: tpmi::RhNvIndex) -> Result<Self, Error> {
+        open(tpm, nv_index, tpm::NT::Counter)?;
+        Ok(Self { nv_index })
+    }
+
+    pub const fn nv_index(&self) -> tpmi::RhNvIndex {
+        self.nv_index
+    }
+
+    /// Increments the counter by one.
+    ///
+    /// A counter must be incremented once before it can be read.
+    pub fn increment<T: TpmRun + ?Sized>(
+        &self,
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+    ) -> Result<(), Error> {
+        tpm.run(NvIncrement {
+            auth_handle,
+            nv_index: self.nv_index,
+        })
+    }
+
+    /// Reads the current value of the counter.
+    pub fn read<T: TpmRun + ?Sized>(
+        &self,
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+    ) -> Result<u64, Error> {
+        read_u64(tpm, auth_handle, self.nv_index)
+    }
+}
+
+/// An NV Index of type [`tpm::NT::Bits`]
+///
+/// Bits can be set (ORed into the current value) but never cleared.
+#[derive(Clone, Copy, Debug)]
+pub struct NvBitField {
+    nv_index: tpmi::RhNvIndex,
+}
+
+impl NvBitField {
+    /// Defines a new bit field. The type and size in `public` are ignored.
+    pub fn define<T: TpmRun + ?Sized>(
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+        auth: &[u8],
+        public: tpms::NvPublic,
+    ) -> Result<Self, Error> {
+        let nv_index = define(tpm, auth_handle, auth, public, tpm::NT::Bits, 8)?;
+        Ok(Self { nv_index })
+    }
+
+    /// Opens an existing bit field.
+    pub fn open<T: TpmRun + ?Sized>(tpm: &mut T, nv_index: tpmi::RhNvIndex) -> Result<Self, Error> {
+        open(tpm, nv_index, tpm::NT::Bits)?;
+        Ok(Self { nv_index })
+    }
+
+    pub const fn nv_index(&self) -> tpmi::RhNvIndex {
+        self.nv_index
+    }
+
+    /// Sets the bits in `bits`, leaving other bits unchanged.
+    pub fn set_bits<T: TpmRun + ?Sized>(
+        &self,
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+        bits: u64,
+    ) -> Result<(), Error> {
+        tpm.run(NvSetBits {
+            auth_handle,
+            nv_index: self.nv_index,
+            bits,
+        })
+    }
+
+    /// Reads the current value of the bit field.
+    pub fn read<T: TpmRun + ?Sized>(
+        &self,
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+    ) -> Result<u64, Error> {
+        read_u64(tpm, auth_handle, self.nv_index)
+    }
+}
+
+/// An NV Index of type [`tpm::NT::Extend`]
+///
+/// Like a PCR, the index starts as all zeros and is updated as
+/// `value = H(value || data)`, where `H` is the name algorithm of the index.
+/// As this crate does not implement any hash algorithms, the expected value of
+/// the index is computed with a caller-provided hash function.
+#[derive(Clone, Copy, Debug)]
+pub struct NvExtendIndex {
+    nv_index: tpmi::RhNvIndex,
+    initial: tpmt::Hash,
+}
+
+impl NvExtendIndex {
+    /// Defines a new extend index. The type and size in `public` are ignored.
+    pub fn define<T: TpmRun + ?Sized>(
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+        auth: &[u8],
+        public: tpms::NvPublic,
+    ) -> Result<Self, Error> {
+        let initial = zero_digest(public.name_alg)?;
+        let size = initial.digest().len() as u16;
+        let nv_index = define(tpm, auth_handle, auth, public, tpm::NT::Extend, size)?;
+        Ok(Self { nv_index, initial })
+    }
+
+    /// Opens an existing extend index.
+    pub fn open<T: TpmRun + ?Sized>(tpm: &mut T, nv_index: tpmi::RhNvIndex) -> Result<Self, Error> {
+        let name_alg = open(tpm, nv_index, tpm::NT::Extend)?;
+        let initial = zero_digest(name_alg)?;
+        Ok(Self { nv_index, initial })
+    }
+
+    pub const fn nv_index(&self) -> tpmi::RhNvIndex {
+        self.nv_index
+    }
+
+    /// Extends `data` into the index.
+    pub fn extend<T: TpmRun + ?Sized>(
+        &self,
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        tpm.run(NvExtend {
+            auth_handle,
+            nv_index: self.nv_index,
+            data,
+        })
+    }
+
+    /// Reads the current value of the index.
+    ///
+    /// An index which has never been extended can't be read by the TPM
+    /// (`TPM_RC_NV_UNINITIALIZED`), so its value is the all-zero digest.
+    pub fn read<T: TpmRun + ?Sized>(
+        &self,
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+    ) -> Result<tpmt::Hash, Error> {
+        let alg = self.initial.alg();
+        let result = tpm.run(NvRead {
+            auth_handle,
+            nv_index: self.nv_index,
+            size: self.initial.digest().len() as u16,
+            offset: 0,
+        });
+        let rsp = match result {
+            Err(e) if e.tpm_error().map(|e| e.base()) == Some(tpm::rc::NV_UNINITIALIZED) => {
+                return Ok(self.initial)
+            }
+            result => result?,
+        };
+        let mut data = rsp.data;
+        let value = tpmt::Hash::unmarshal_with_alg(alg, &mut data)?;
+        if !data.is_empty() {
+            return Err(UnmarshalError::BufferRemaining.into());
+        }
+        Ok(value)
+    }
+
+    /// Computes the value the index should have after extending each of
+    /// `events` (in order) into a newly defined index.
+    ///
+    /// `hash` is called with the name algorithm of the index and the pieces
+    /// of data to be hashed. It must return their concatenated digest.
+    pub fn expected_value<'a>(
+        &self,
+        events: impl IntoIterator<Item = &'a [u8]>,
+        mut hash: impl FnMut(tpmi::AlgHash, &[&[u8]]) -> tpmt::Hash,
+    ) -> tpmt::Hash {
+        let alg = self.initial.alg();
+        events.into_iter().fold(self.initial, |value, data| {
+            hash(alg, &[value.digest(), data])
+        })
+    }
+
+    /// Returns true if the current value of the index is `expected`.
+    pub fn matches<T: TpmRun + ?Sized>(
+        &self,
+        tpm: &mut T,
+        auth_handle: AuthHandle,
+        expected: &tpmt::Hash,
+    ) -> Result<bool, Error> {
+        Ok(self.read(tpm, auth_handle)? == *expected)
+    }
+}
+
+/// Returns the all-zero digest for `alg`.
+fn zero_digest(alg: tpmi::AlgHash) -> Result<tpmt::Hash, Error> {
+    let zeros = [0u8; 64];
+    Ok(tpmt::Hash::unmarshal_with_alg(alg, &mut &zeros[..])?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{format, vec::Vec};
+
+    use super::*;
+    use crate::{
+        error::ErrorKind,
+        test::ScriptTpm,
+        types::{tpma, PasswordAuth},
+    };
+
+    /// Opens each wrapper on a fake index of type `nt`, returning the errors.
+    fn open_all(nt: tpm::NT) -> [Option<NvTypeError>; 3] {
+        let mut tpm = ScriptTpm::new(|code, _: &[u8]| match code {
+            tpm::CC::NvReadPublic => {
+                let attrs = tpma::Nv::empty().with_nt(nt);
+                (0, crate::ext::test::nv_public(attrs, 32))
+            }
+            _ => (tpm::rc::COMMAND_CODE, Vec::new()),
+        });
+        let nv_index = tpm::hc::NV_INDEX_FIRST;
+        let err = |e: Error| match e.into_kind() {
+            ErrorKind::NvType(e) => e,
+            e => panic!("unexpected error: {e}"),
+        };
+        [
+            NvCounter::open(&mut tpm, nv_index).err().map(err),
+            NvBitField::open(&mut tpm, nv_index).err().map(err),
+            NvExtendIndex::open(&mut tpm, nv_index).err().map(err),
+        ]
+    }
+
+    #[test]
+    fn open_wrong_type() {
+        let [counter, bits, extend] = open_all(tpm::NT::Ordinary);
+        for (e, expected) in [
+            (counter, tpm::NT::Counter),
+            (bits, tpm::NT::Bits),
+            (extend, tpm::NT::Extend),
+        ] {
+            let e = e.unwrap();
+            assert_eq!(e.expected, expected);
+            assert_eq!(e.actual, Some(tpm::NT::Ordinary));
+        }
+        let e = open_all(tpm::NT::Counter)[1].unwrap();
+        assert_eq!(
+            format!("{e}"),
+            "NV Index 0x01000000 has type Counter, expected Bits"
+        );
+
+        assert!(open_all(tpm::NT::Counter)[0].is_none());
+        assert!(open_all(tpm::NT::Bits)[1].is_none());
+        assert!(open_all(tpm::NT::Extend)[2].is_none());
+    }
+
+    #[test]
+    fn extend_expected_value() {
+        let index = NvExtendIndex {
+            nv_index: tpm::hc::NV_INDEX_FIRST,
+            initial: zero_digest(tpm::Alg::Sha1).unwrap(),
+        };
+        // A fake "hash" that adds the bytes of each piece into the first byte
+        let value = index.expected_value([&[1u8, 2][..], &[3]], |alg, data| {
+            assert_eq!(alg, tpm::Alg::Sha1);
+            assert_eq!(data.len(), 2);
+            assert_eq!(data[0].len(), 20);
+            let mut d = [0; 20];
+            d[0] = data.iter().flat_map(|p| p.iter()).sum();
+            tpmt::Hash::Sha1(d)
+        });
+        assert_eq!(value.digest()[0], 6);
+        assert_eq!(
+            index.expected_value([], |_, _| unreachable!()),
+            index.initial
+        );
+    }
+
+    #[test]
+    fn read_unextended_index() {
+        let mut tpm = ScriptTpm::new(|code, _: &[u8]| match code {
+            tpm::CC::NvRead => (tpm::rc::NV_UNINITIALIZED, Vec::new()),
+            _ => (tpm::rc::COMMAND_CODE, Vec::new()),
+        });
+        let index = NvExtendIndex {
+            nv_index: tpm::hc::NV_INDEX_FIRST,
+            initial: zero_digest(tpm::Alg::Sha256).unwrap(),
+        };
+        let auth = PasswordAuth(&[]);
+        let auth_handle = AuthHandle {
+            handle: index.nv_index,
+            auth: &auth,
+        };
+        let expected = index.expected_value([], |_, _| unreachable!());
+        assert!(index.matches(&mut tpm, auth_handle, &expected).unwrap());
+        assert_eq!(tpm.commands, [tpm::CC::NvRead]);
+    }
+}
@@ -14,6 +14,6 @@ fn open_tpm() -> io::Result<File> {
     Ok(f)
 }
 
-pub(crate) fn default_impl() -> io::Result<impl Tpm> {
+pub(crate) fn default_impl() -> io::Result<impl Tpm + Send + 'static> {
     Ok(tpm_from_read_write(open_tpm()?))
 }
@@ -4,7 +4,17 @@
 
 use std::{io, prelude::v1::*};
 
-use crate::{error::DriverError, polyfill::ToUsize, Tpm};
+use crate::{
+    error::DriverError,
+    ext::get_tpm_property,
+    polyfill::ToUsize,
+    types::{tpm, ResponseHeader},
+    Error, Tpm,
+};
+
+#[cfg(feature = "tokio")]
+#[doc(cfg(feature = "tokio"))]
+pub mod tokio;
 
 // Keep in sync with default_tpm cfg
 cfg_if::cfg_if! {
@@ -17,12 +27,89 @@ cfg_if::cfg_if! {
     }
 }
 
-struct RwTpm<RW> {
+/// Default size of the command buffer and maximum size of a response
+const DEFAULT_BUFFER_SIZE: usize = 4096;
+
+/// A [`Tpm`] sending commands to a [`Write`](io::Write) and reading responses
+/// from a [`Read`](io::Read)
+///
+/// By default, each response is read until EOF, which is how the `/dev/tpm*`
+/// character devices behave. Pipes, sockets and serial links never return
+/// EOF, so should use [`RwTpm::framed`], which reads the response header and
+/// then exactly the number of bytes it specifies.
+#[derive(Debug)]
+pub struct RwTpm<RW> {
     cmd: Box<[u8]>,
     rsp: Vec<u8>,
+    max_response_size: usize,
+    framed: bool,
     rw: RW,
 }
 
+impl<RW: io::Read + io::Write> RwTpm<RW> {
+    /// Reads each response until EOF.
+    pub fn new(rw: RW) -> Self {
+        Self {
+            cmd: vec![0; DEFAULT_BUFFER_SIZE].into_boxed_slice(),
+            rsp: vec![],
+            max_response_size: DEFAULT_BUFFER_SIZE,
+            framed: false,
+            rw,
+        }
+    }
+
+    /// Reads each response using the size in its header.
+    pub fn framed(rw: RW) -> Self {
+        Self {
+            framed: true,
+            ..Self::new(rw)
+        }
+    }
+
+    /// Sets the size of the command buffer and the maximum response size
+    /// (4096 bytes by default).
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.cmd = vec![0; size].into_boxed_slice();
+        self.max_response_size = size;
+        self
+    }
+
+    /// Sizes the command buffer and maximum response size using the
+    /// TPM_PT_MAX_COMMAND_SIZE and TPM_PT_MAX_RESPONSE_SIZE properties.
+    pub fn size_from_tpm(&mut self) -> Result<(), Error> {
+        let cmd_size = get_tpm_property(self, tpm::pt::MAX_COMMAND_SIZE)?;
+        let rsp_size = get_tpm_property(self, tpm::pt::MAX_RESPONSE_SIZE)?;
+        self.cmd = vec![0; cmd_size.to_usize()].into_boxed_slice();
+        self.max_response_size = rsp_size.to_usize();
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> RW {
+        self.rw
+    }
+
+    fn read_framed(&mut self) -> io::Result<()> {
+        self.rsp.resize(ResponseHeader::SIZE, 0);
+        self.rw.read_exact(&mut self.rsp)?;
+        let size = framed_response_size(&self.rsp, self.max_response_size)?;
+        self.rsp.resize(size, 0);
+        self.rw.read_exact(&mut self.rsp[ResponseHeader::SIZE..])
+    }
+}
+
+/// Returns the response size from a response header, checking that it is
+/// between the header size and `max_size`.
+fn framed_response_size(header: &[u8], max_size: usize) -> io::Result<usize> {
+    let size = u32::from_be_bytes(header[2..6].try_into().unwrap()).to_usize();
+    if !(ResponseHeader::SIZE..=max_size).contains(&size) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid TPM response size",
+        ));
+    }
+    Ok(size)
+}
+
 impl<RW: io::Read + io::Write> Tpm for RwTpm<RW> {
     fn command_buf(&mut self) -> &mut [u8] {
         &mut self.cmd
@@ -35,24 +122,73 @@ impl<RW: io::Read + io::Write> Tpm for RwTpm<RW> {
     fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
         self.rw.write_all(&self.cmd[..cmd_size.to_usize()])?;
         self.rsp.clear();
-        self.rw.read_to_end(&mut self.rsp)?;
+        if self.framed {
+            self.read_framed()?;
+        } else {
+            self.rw.read_to_end(&mut self.rsp)?;
+        }
         Ok(())
     }
 }
 
-/// TODO: explain why you would want this
-pub fn tpm_from_read_write(rw: impl io::Read + io::Write) -> impl Tpm {
-    RwTpm {
-        cmd: vec![0; 4096].into_boxed_slice(),
-        rsp: vec![],
-        rw,
-    }
+/// Creates a [`Tpm`] which reads each response until EOF, see [`RwTpm`].
+///
+/// The returned TPM is [`Send`] if `rw` is.
+pub fn tpm_from_read_write<RW: io::Read + io::Write>(rw: RW) -> RwTpm<RW> {
+    RwTpm::new(rw)
 }
 
 /// TODO: Document this for Linux and Windows
+///
+/// The returned TPM is [`Send`], so it can be used from many threads with
+/// [`SharedTpm`](crate::wrap::SharedTpm).
 // Keep in sync with cfg_if
 #[cfg(any(target_os = "linux", windows))]
 #[doc(cfg(any(target_os = "linux", windows)))]
-pub fn default_tpm() -> io::Result<impl Tpm> {
+pub fn default_tpm() -> io::Result<impl Tpm + Send + 'static> {
     default_impl()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Reads from a fixed buffer (without EOF) and discards writes
+    struct Pipe<'a>(&'a [u8]);
+
+    impl io::Read for Pipe<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.0.read(buf)
+        }
+    }
+    impl io::Write for Pipe<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn framed() {
+        let data = [0x80, 0x01, 0, 0, 0, 12, 0, 0, 0, 0, 1, 2, 0x80];
+        let mut tpm = RwTpm::framed(Pipe(&data));
+        tpm.execute_command(0).unwrap();
+        assert_eq!(tpm.response_buf(), &data[..12]);
+        assert_eq!(tpm.into_inner().0, [0x80]);
+
+        let mut tpm = RwTpm::framed(Pipe(&data)).buffer_size(11);
+        assert_eq!(tpm.command_buf().len(), 11);
+        assert!(tpm.execute_command(0).is_err());
+    }
+
+    #[test]
+    fn send() {
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&tpm_from_read_write(Pipe(&[])));
+    }
+}
@@ -0,0 +1,274 @@
+//! [`AsyncTpm`] implementations using Tokio
+//!
+//! Local TPMs (such as the Linux `/dev/tpm*` character devices) only support
+//! blocking I/O, so [`BlockingTpm`] runs them on Tokio's blocking thread pool.
+//! TPMs reachable over a socket (such as a TCP or Unix socket exposed by
+//! `swtpm`) are supported by [`StreamTpm`].
+
+use std::{io, prelude::v1::*};
+
+use ::tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpStream, ToSocketAddrs},
+    task::{spawn_blocking, JoinHandle},
+};
+
+use super::{framed_response_size, DEFAULT_BUFFER_SIZE};
+use crate::{error::DriverError, polyfill::ToUsize, types::ResponseHeader, AsyncTpm, Tpm};
+
+/// An [`AsyncTpm`] running a blocking [`Tpm`] on Tokio's blocking thread pool
+///
+/// While a command is running, the inner TPM is moved to the blocking thread,
+/// so commands and responses are copied through buffers owned by this type.
+/// If the future running a command is dropped before the command completes,
+/// the next command waits for it to finish (discarding its response) before
+/// being sent. If the blocking thread panics, the inner TPM is lost and all
+/// further commands fail.
+#[derive(Debug)]
+pub struct BlockingTpm<T> {
+    cmd: Box<[u8]>,
+    rsp: Vec<u8>,
+    tpm: Option<T>,
+    running: Option<JoinHandle<(T, Result<(), DriverError>)>>,
+}
+
+impl<T: Tpm + Send + 'static> BlockingTpm<T> {
+    pub fn new(mut tpm: T) -> Self {
+        Self {
+            cmd: vec![0; tpm.command_buf().len()].into_boxed_slice(),
+            rsp: vec![],
+            tpm: Some(tpm),
+            running: None,
+        }
+    }
+
+    /// Returns the inner TPM, if it has not been lost and is not still
+    /// running a cancelled command.
+    pub fn into_inner(self) -> Option<T> {
+        self.tpm
+    }
+}
+
+impl<T: Tpm + Send + 'static> AsyncTpm for BlockingTpm<T> {
+    fn command_buf(&mut self) -> &mut [u8] {
+        &mut self.cmd
+    }
+
+    fn response_buf(&self) -> &[u8] {
+        &self.rsp
+    }
+
+    async fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
+        self.rsp.clear();
+        if let Some(running) = self.running.take() {
+            // A previous command was cancelled, so recover the TPM from it.
+            let (tpm, _) = running.await.map_err(io::Error::other)?;
+            self.tpm = Some(tpm);
+        }
+        let Some(mut tpm) = self.tpm.take() else {
+            return Err(io::Error::other("TPM lost by a previous command").into());
+        };
+        let size = cmd_size.to_usize();
+        tpm.command_buf()[..size].copy_from_slice(&self.cmd[..size]);
+        let running = self.running.insert(spawn_blocking(move || {
+            let result = tpm.execute_command(cmd_size);
+            (tpm, result)
+        }));
+        let joined = running.await;
+        self.running = None;
+        let (tpm, result) = joined.map_err(io::Error::other)?;
+        self.rsp.extend_from_slice(tpm.response_buf());
+        self.tpm = Some(tpm);
+        result
+    }
+}
+
+/// Opens the default TPM (see [`default_tpm`](super::default_tpm)) for use
+/// with Tokio.
+// Keep in sync with super::default_tpm
+#[cfg(any(target_os = "linux", windows))]
+#[doc(cfg(any(target_os = "linux", windows)))]
+pub fn default_tpm() -> io::Result<BlockingTpm<impl Tpm + Send + 'static>> {
+    Ok(BlockingTpm::new(super::default_tpm()?))
+}
+
+/// An [`AsyncTpm`] using a stream (such as a TCP or Unix socket)
+///
+/// Streams do not signal the end of a response, so each response is read
+/// using the size in its header.
+///
+/// If the future running a command is dropped (or fails) after the command is
+/// sent but before its response is fully read, the stream no longer lines up
+/// with the commands, and all further commands fail.
+#[derive(Debug)]
+pub struct StreamTpm<S> {
+    cmd: Box<[u8]>,
+    rsp: Vec<u8>,
+    max_response_size: usize,
+    in_flight: bool,
+    stream: S,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> StreamTpm<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            cmd: vec![0; DEFAULT_BUFFER_SIZE].into_boxed_slice(),
+            rsp: vec![],
+            max_response_size: DEFAULT_BUFFER_SIZE,
+            in_flight: false,
+            stream,
+        }
+    }
+
+    /// Sets the size of the command buffer and the maximum response size
+    /// (4096 bytes by default).
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.cmd = vec![0; size].into_boxed_slice();
+        self.max_response_size = size;
+        self
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> AsyncTpm for StreamTpm<S> {
+    fn command_buf(&mut self) -> &mut [u8] {
+        &mut self.cmd
+    }
+
+    fn response_buf(&self) -> &[u8] {
+        &self.rsp
+    }
+
+    async fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
+        if self.in_flight {
+            return Err(io::Error::other("stream left mid-command by a previous command").into());
+        }
+        self.in_flight = true;
+        self.stream
+            .write_all(&self.cmd[..cmd_size.to_usize()])
+            .await?;
+        self.rsp.resize(ResponseHeader::SIZE, 0);
+        self.stream.read_exact(&mut self.rsp).await?;
+        let size = framed_response_size(&self.rsp, self.max_response_size)?;
+        self.rsp.resize(size, 0);
+        self.stream
+            .read_exact(&mut self.rsp[ResponseHeader::SIZE..])
+            .await?;
+        self.in_flight = false;
+        Ok(())
+    }
+}
+
+/// Connects to a TPM listening on a TCP socket.
+pub async fn connect_tcp(addr: impl ToSocketAddrs) -> io::Result<StreamTpm<TcpStream>> {
+    Ok(StreamTpm::new(TcpStream::connect(addr).await?))
+}
+
+/// Connects to a TPM listening on a Unix socket.
+#[cfg(unix)]
+#[doc(cfg(unix))]
+pub async fn connect_unix(
+    path: impl AsRef<std::path::Path>,
+) -> io::Result<StreamTpm<::tokio::net::UnixStream>> {
+    Ok(StreamTpm::new(
+        ::tokio::net::UnixStream::connect(path).await?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Waker},
+    };
+    use std::sync::mpsc;
+
+    use ::tokio::{io::duplex, runtime::Builder};
+
+    use super::*;
+    use crate::{
+        commands::GetRandom, error::ErrorKind, test::ScriptTpm, types::PasswordAuth,
+        wrap::test::sequence_tpm, AsyncTpmRun, Command,
+    };
+
+    fn block_on<F: core::future::Future>(f: F) -> F::Output {
+        Builder::new_current_thread().build().unwrap().block_on(f)
+    }
+
+    #[test]
+    fn blocking_tpm() {
+        let mut tpm = BlockingTpm::new(sequence_tpm(&[]));
+        let auth = PasswordAuth(&[]);
+        let cmd = GetRandom { bytes_requested: 0 }.with_auth(&auth);
+        let fut = tpm.run(cmd);
+        // Commands (including their auths) can be run on any thread.
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&fut);
+        block_on(fut).unwrap();
+        assert_eq!(tpm.into_inner().unwrap().commands.len(), 1);
+    }
+
+    #[test]
+    fn blocking_tpm_cancelled() {
+        // Each command blocks until it is released
+        let (release, gate) = mpsc::channel();
+        let tpm = ScriptTpm::new(move |_, _: &[u8]| {
+            gate.recv().unwrap();
+            (0, vec![0, 0])
+        });
+        let mut tpm = BlockingTpm::new(tpm);
+        block_on(async {
+            {
+                // Start the command, then drop the future while it is running.
+                let fut = pin!(tpm.run(GetRandom { bytes_requested: 0 }));
+                let mut cx = Context::from_waker(Waker::noop());
+                assert!(fut.poll(&mut cx).is_pending());
+            }
+            release.send(()).unwrap();
+            release.send(()).unwrap();
+            tpm.run(GetRandom { bytes_requested: 0 }).await.unwrap();
+        });
+        assert_eq!(tpm.into_inner().unwrap().commands.len(), 2);
+    }
+
+    #[test]
+    fn stream_tpm() {
+        let (client, mut server) = duplex(64);
+        let mut tpm = StreamTpm::new(client);
+        block_on(async {
+            let rsp = [0x80, 0x01, 0, 0, 0, 14, 0, 0, 0, 0, 0, 2, 7, 8];
+            server.write_all(&rsp).await.unwrap();
+            let rsp = tpm.run(GetRandom { bytes_requested: 2 }).await.unwrap();
+            assert_eq!(rsp.random_bytes, [7, 8]);
+
+            let mut cmd = [0; 12];
+            server.read_exact(&mut cmd).await.unwrap();
+            assert_eq!(cmd, [0x80, 0x01, 0, 0, 0, 12, 0, 0, 1, 0x7B, 0, 2]);
+        });
+    }
+
+    #[test]
+    fn stream_tpm_cancelled() {
+        let (client, mut server) = duplex(64);
+        let mut tpm = StreamTpm::new(client);
+        {
+            // Send the command, then drop the future while awaiting the response.
+            let fut = pin!(tpm.run(GetRandom { bytes_requested: 2 }));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(fut.poll(&mut cx).is_pending());
+        }
+        block_on(async {
+            let mut cmd = [0; 12];
+            server.read_exact(&mut cmd).await.unwrap();
+            // A late response must not be taken as the next command's.
+            let rsp = [0x80, 0x01, 0, 0, 0, 14, 0, 0, 0, 0, 0, 2, 7, 8];
+            server.write_all(&rsp).await.unwrap();
+            let err = tpm.run(GetRandom { bytes_requested: 2 }).await.unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Driver(_)));
+        });
+    }
+}
@@ -3,8 +3,10 @@
 use core::fmt::Debug;
 
 use crate::{
-    error::{DriverError, Error, MarshalError, UnmarshalError},
-    marshal::{pop_array_mut, CommandData, Marshal, MarshalFixed, ResponseData, Unmarshal},
+    error::{Context, DriverError, Error, MarshalError, Part, TpmError, UnmarshalError},
+    marshal::{
+        pop_array_mut, read_u32, CommandData, Marshal, MarshalFixed, ResponseData, Unmarshal,
+    },
     polyfill::ToUsize,
     types::{tpm, tpms::AuthResponse, Auth, CommandHeader, ResponseHeader},
 };
@@ -54,6 +56,22 @@ impl<C: CommandData> CommandData for &C {
     fn marshal_params(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
         (*self).marshal_params(buf)
     }
+    #[inline]
+    fn marshal_handles_traced(
+        &self,
+        buf: &mut &mut [u8],
+        field: &mut Option<&'static str>,
+    ) -> Result<(), MarshalError> {
+        (*self).marshal_handles_traced(buf, field)
+    }
+    #[inline]
+    fn marshal_params_traced(
+        &self,
+        buf: &mut &mut [u8],
+        field: &mut Option<&'static str>,
+    ) -> Result<(), MarshalError> {
+        (*self).marshal_params_traced(buf, field)
+    }
 }
 impl<C: Command> Command for &C {
     const CODE: tpm::CC = C::CODE;
@@ -79,6 +97,22 @@ impl<C: CommandData> CommandData for WithAuth<'_, C> {
     fn marshal_params(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
         self.0.marshal_params(buf)
     }
+    #[inline]
+    fn marshal_handles_traced(
+        &self,
+        buf: &mut &mut [u8],
+        field: &mut Option<&'static str>,
+    ) -> Result<(), MarshalError> {
+        self.0.marshal_handles_traced(buf, field)
+    }
+    #[inline]
+    fn marshal_params_traced(
+        &self,
+        buf: &mut &mut [u8],
+        field: &mut Option<&'static str>,
+    ) -> Result<(), MarshalError> {
+        self.0.marshal_params_traced(buf, field)
+    }
 }
 impl<C: Command> Command for WithAuth<'_, C> {
     const CODE: tpm::CC = C::CODE;
@@ -114,6 +148,21 @@ pub trait Tpm {
     fn command_buf(&mut self) -> &mut [u8];
     fn response_buf(&self) -> &[u8];
     fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError>;
+
+    /// Called when the TPM returns `err` for the command `code`, where
+    /// `attempt` is the number of times the command has failed so far.
+    /// Wrappers delegating to an inner TPM don't count the failures they
+    /// retried themselves, so each wrapper sees `attempt == 1` on the first
+    /// failure it is asked about.
+    ///
+    /// Returning `Ok(true)` re-marshals and re-executes the command. The
+    /// default implementation never retries; see [`wrap`](crate::wrap) for
+    /// wrappers which do.
+    #[inline]
+    fn should_retry(&mut self, code: tpm::CC, err: TpmError, attempt: u32) -> Result<bool, Error> {
+        let _ = (code, err, attempt);
+        Ok(false)
+    }
 }
 
 /// Trait extending [`Tpm`] for running raw commands.
@@ -122,6 +171,15 @@ pub trait TpmRun: Tpm {
         &mut self,
         cmd: C,
     ) -> Result<C::Response<'_>, Error>;
+
+    /// Runs `cmd`, returning the entire response (including the header)
+    /// rather than unmarshalling its parameters.
+    ///
+    /// The response header, handles and sessions are still checked as in
+    /// [`run`](TpmRun::run), so this is useful when `C::Response` doesn't
+    /// (yet) describe all the parameters the TPM returns.
+    fn run_parsed<C: Command + Auths<N>, const N: usize>(&mut self, cmd: C)
+        -> Result<&[u8], Error>;
 }
 
 impl<T: Tpm> TpmRun for T {
@@ -132,6 +190,13 @@ impl<T: Tpm> TpmRun for T {
     ) -> Result<C::Response<'_>, Error> {
         <dyn Tpm>::run(self, cmd)
     }
+    #[inline]
+    fn run_parsed<C: Command + Auths<N>, const N: usize>(
+        &mut self,
+        cmd: C,
+    ) -> Result<&[u8], Error> {
+        <dyn Tpm>::run_parsed(self, cmd)
+    }
 }
 impl TpmRun for dyn Tpm + '_ {
     #[inline]
@@ -143,6 +208,60 @@ impl TpmRun for dyn Tpm + '_ {
         run_impl(self, &cmd.auths(), cmd.data(), &mut rsp, C::CODE)?;
         Ok(rsp)
     }
+    #[inline]
+    fn run_parsed<C: Command + Auths<N>, const N: usize>(
+        &mut self,
+        cmd: C,
+    ) -> Result<&[u8], Error> {
+        let auths = cmd.auths();
+        let mut rsp = RawParams(C::Response::default());
+        execute_retry(self, &auths, cmd.data(), C::CODE)?;
+        let rsp_buf = self.response_buf();
+        unmarshal_response(rsp_buf, &auths, &mut rsp, C::CODE)?;
+        Ok(rsp_buf)
+    }
+}
+
+/// Response which unmarshals handles with `R`, but skips the parameters
+struct RawParams<R>(R);
+
+impl<'t, R: ResponseData<'t>> ResponseData<'t> for RawParams<R> {
+    fn unmarshal_handles(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        self.0.unmarshal_handles(buf)
+    }
+    fn unmarshal_params(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        *buf = &[];
+        Ok(())
+    }
+    fn unmarshal_handles_traced(
+        &mut self,
+        buf: &mut &'t [u8],
+        field: &mut Option<&'static str>,
+    ) -> Result<(), UnmarshalError> {
+        self.0.unmarshal_handles_traced(buf, field)
+    }
+}
+
+/// Returns a function attaching `part` of `command` (and `offset`) to an error.
+fn context<E: Into<Error>>(command: tpm::CC, part: Part, offset: usize) -> impl FnOnce(E) -> Error {
+    field_context(command, part, offset, None)
+}
+
+/// Like [`context`], but also attaches the structure `field` which failed.
+fn field_context<E: Into<Error>>(
+    command: tpm::CC,
+    part: Part,
+    offset: usize,
+    field: Option<&'static str>,
+) -> impl FnOnce(E) -> Error {
+    move |e| {
+        e.into().with_context(Context {
+            command,
+            part: Some(part),
+            offset: Some(offset),
+            field,
+        })
+    }
 }
 
 // This function is intentionally non-generic to reduce code size.
@@ -153,80 +272,493 @@ fn run_impl<'a>(
     rsp: &mut dyn ResponseData<'a>,
     code: tpm::CC,
 ) -> Result<(), Error> {
-    //// Marshal Command
-    let mut cmd_buf = tpm.command_buf();
+    execute_retry(tpm, auths, cmd, code)?;
+    unmarshal_response(tpm.response_buf(), auths, rsp, code)
+}
+
+/// Executes a command, retrying while [`Tpm::should_retry`] returns true.
+fn execute_retry(
+    tpm: &mut dyn Tpm,
+    auths: &[&dyn Auth],
+    cmd: &dyn CommandData,
+    code: tpm::CC,
+) -> Result<(), Error> {
+    // Each attempt re-marshals the command, so sessions can update nonces.
+    let mut attempt = 0;
+    while let Err(err) = execute_impl(tpm, auths, cmd, code) {
+        attempt += 1;
+        match err.tpm_error() {
+            Some(tpm_err) if tpm.should_retry(code, tpm_err, attempt)? => continue,
+            _ => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Marshals and executes a command, then checks the response header.
+fn execute_impl(
+    tpm: &mut dyn Tpm,
+    auths: &[&dyn Auth],
+    cmd: &dyn CommandData,
+    code: tpm::CC,
+) -> Result<(), Error> {
+    let cmd_header = marshal_command(tpm.command_buf(), auths, cmd, code)?;
+
+    //// Execute the command
+    tpm.execute_command(cmd_header.size)
+        .map_err(|e| Error::from(e).with_context(Context::for_command(code)))?;
+
+    check_response_header(tpm.response_buf(), &cmd_header)
+}
+
+/// Executes the already marshalled `cmd`, checking only that the size in the
+/// header of the command (and of the response) matches its length.
+pub(crate) fn execute_raw<T: Tpm + ?Sized>(tpm: &mut T, cmd: &[u8]) -> Result<(), Error> {
+    let code = read_u32(cmd, 6).map_or(tpm::CC::default(), tpm::CC::new);
+    let size = match read_u32(cmd, 2) {
+        Some(size) if cmd.len() >= CommandHeader::SIZE && size.to_usize() == cmd.len() => size,
+        _ => {
+            return Err(context(code, Part::CommandHeader, 2)(
+                MarshalError::SizeMismatch,
+            ))
+        }
+    };
+    tpm.command_buf()
+        .get_mut(..cmd.len())
+        .ok_or(MarshalError::BufferOverflow)
+        .map_err(context(code, Part::CommandHeader, 0))?
+        .copy_from_slice(cmd);
+
+    tpm.execute_command(size)
+        .map_err(|e| Error::from(e).with_context(Context::for_command(code)))?;
+
+    let rsp = tpm.response_buf();
+    match read_u32(rsp, 2) {
+        Some(size) if rsp.len() >= ResponseHeader::SIZE && size.to_usize() == rsp.len() => Ok(()),
+        _ => Err(context(code, Part::ResponseHeader, 2)(
+            UnmarshalError::SizeMismatch,
+        )),
+    }
+}
+
+/// Marshals a command into `cmd_buf`, returning its header.
+pub(crate) fn marshal_command(
+    mut cmd_buf: &mut [u8],
+    auths: &[&dyn Auth],
+    cmd: &dyn CommandData,
+    code: tpm::CC,
+) -> Result<CommandHeader, Error> {
     let buf_len = cmd_buf.len();
+    let mut field = None;
     // Marshal the header at the end
-    let header_buf: &mut [u8; CommandHeader::SIZE] = pop_array_mut(&mut cmd_buf)?;
+    let header_buf: &mut [u8; CommandHeader::SIZE] =
+        pop_array_mut(&mut cmd_buf).map_err(context(code, Part::CommandHeader, 0))?;
 
     // Marshal Handles
-    cmd.marshal_handles(&mut cmd_buf)?;
+    cmd.marshal_handles_traced(&mut cmd_buf, &mut field)
+        .map_err(field_context(
+            code,
+            Part::CommandHandles,
+            buf_len - cmd_buf.len(),
+            field,
+        ))?;
 
     // Marshal Authorization Area
     if !auths.is_empty() {
         // Marshal auth size at the end
-        let auth_size_buf: &mut [u8; 4] = pop_array_mut(&mut cmd_buf)?;
+        let auth_size_buf: &mut [u8; 4] = pop_array_mut(&mut cmd_buf).map_err(context(
+            code,
+            Part::CommandAuths,
+            buf_len - cmd_buf.len(),
+        ))?;
         let cmd_buf_len = cmd_buf.len();
 
         for auth in auths {
-            auth.get_auth().marshal(&mut cmd_buf)?;
+            auth.get_auth()
+                .marshal_traced(&mut cmd_buf, &mut field)
+                .map_err(field_context(
+                    code,
+                    Part::CommandAuths,
+                    buf_len - cmd_buf.len(),
+                    field,
+                ))?;
         }
 
-        let auth_size: u32 = (cmd_buf_len - cmd_buf.len()).try_into().unwrap();
+        let auth_size: u32 = (cmd_buf_len - cmd_buf.len())
+            .try_into()
+            .map_err(MarshalError::from)
+            .map_err(context(code, Part::CommandAuths, buf_len - cmd_buf.len()))?;
         auth_size.marshal_fixed(auth_size_buf);
     }
 
     // Marshal Parameters
-    cmd.marshal_params(&mut cmd_buf)?;
+    cmd.marshal_params_traced(&mut cmd_buf, &mut field)
+        .map_err(field_context(
+            code,
+            Part::CommandParams,
+            buf_len - cmd_buf.len(),
+            field,
+        ))?;
 
     // Marshal Header
+    let size: u32 = (buf_len - cmd_buf.len())
+        .try_into()
+        .map_err(MarshalError::from)
+        .map_err(context(code, Part::CommandHeader, 0))?;
     let cmd_header = CommandHeader {
         tag: if auths.is_empty() {
             tpm::ST::NoSessions
         } else {
             tpm::ST::Sessions
         },
-        size: (buf_len - cmd_buf.len()).try_into().unwrap(),
+        size,
         code,
     };
     cmd_header.marshal_fixed(header_buf);
 
-    //// Execute the command
-    tpm.execute_command(cmd_header.size)?;
+    Ok(cmd_header)
+}
 
-    //// Unmarshal Response
-    let mut rsp_buf: &'a [u8] = tpm.response_buf();
+/// Checks that a response matches the command with header `cmd_header`.
+pub(crate) fn check_response_header(
+    mut rsp_buf: &[u8],
+    cmd_header: &CommandHeader,
+) -> Result<(), Error> {
+    let code = cmd_header.code;
     let rsp_len = rsp_buf.len();
-    let rsp_header = ResponseHeader::unmarshal_val(&mut rsp_buf)?;
+    let rsp_header = ResponseHeader::unmarshal_val(&mut rsp_buf).map_err(context(
+        code,
+        Part::ResponseHeader,
+        0,
+    ))?;
 
-    // Check for errors
-    assert!(rsp_header.size.to_usize() == rsp_len);
+    if rsp_header.size.to_usize() != rsp_len {
+        return Err(context(code, Part::ResponseHeader, 2)(
+            UnmarshalError::SizeMismatch,
+        ));
+    }
     if let Some(tpm_err) = rsp_header.code {
-        return Err(Error::Tpm(tpm_err));
+        return Err(Error::from(tpm_err).with_context(Context::for_tpm_error(code, tpm_err)));
+    }
+    if rsp_header.tag != cmd_header.tag {
+        return Err(context(code, Part::ResponseHeader, 0)(
+            UnmarshalError::TagMismatch,
+        ));
     }
-    assert!(rsp_header.tag == cmd_header.tag);
+    Ok(())
+}
+
+/// Unmarshals a response (after the header has been checked).
+pub(crate) fn unmarshal_response<'a>(
+    mut rsp_buf: &'a [u8],
+    auths: &[&dyn Auth],
+    rsp: &mut dyn ResponseData<'a>,
+    code: tpm::CC,
+) -> Result<(), Error> {
+    let rsp_len = rsp_buf.len();
+    let mut field = None;
+    // The header was already checked by check_response_header
+    ResponseHeader::unmarshal_val(&mut rsp_buf).map_err(context(code, Part::ResponseHeader, 0))?;
 
     // Unmarshal Handles
-    rsp.unmarshal_handles(&mut rsp_buf)?;
+    rsp.unmarshal_handles_traced(&mut rsp_buf, &mut field)
+        .map_err(field_context(
+            code,
+            Part::ResponseHandles,
+            rsp_len - rsp_buf.len(),
+            field,
+        ))?;
 
     // Unmarshal Authorization Area
+    let mut params_start = rsp_len - rsp_buf.len();
     if !auths.is_empty() {
-        let param_size = u32::unmarshal_val(&mut rsp_buf)?;
+        let param_size = u32::unmarshal_val(&mut rsp_buf).map_err(context(
+            code,
+            Part::ResponseParams,
+            params_start,
+        ))?;
+        params_start += 4;
+        let param_size = param_size.to_usize();
+        if param_size > rsp_buf.len() {
+            return Err(context(code, Part::ResponseParams, params_start)(
+                UnmarshalError::BufferOverflow,
+            ));
+        }
         let mut auth_buf: &[u8];
-        (rsp_buf, auth_buf) = rsp_buf.split_at(param_size.try_into().unwrap());
+        (rsp_buf, auth_buf) = rsp_buf.split_at(param_size);
 
         let mut auth_rsp = AuthResponse::default();
         for auth in auths {
-            auth_rsp.unmarshal(&mut auth_buf)?;
-            auth.set_auth(&auth_rsp)?;
+            let auth_offset = rsp_len - auth_buf.len();
+            auth_rsp
+                .unmarshal_traced(&mut auth_buf, &mut field)
+                .map_err(field_context(
+                    code,
+                    Part::ResponseAuths,
+                    rsp_len - auth_buf.len(),
+                    field,
+                ))?;
+            auth.set_auth(&auth_rsp)
+                .map_err(context(code, Part::ResponseAuths, auth_offset))?;
+        }
+        if !auth_buf.is_empty() {
+            return Err(
+                context(code, Part::ResponseAuths, rsp_len - auth_buf.len())(
+                    UnmarshalError::BufferRemaining,
+                ),
+            );
         }
-        assert!(auth_buf.is_empty());
     }
 
     // Unmarshal Parameters
-    rsp.unmarshal_params(&mut rsp_buf)?;
+    let params_len = rsp_buf.len();
+    rsp.unmarshal_params_traced(&mut rsp_buf, &mut field)
+        .map_err(field_context(
+            code,
+            Part::ResponseParams,
+            params_start + params_len - rsp_buf.len(),
+            field,
+        ))?;
     if !rsp_buf.is_empty() {
-        return Err(Error::Unmarshal(UnmarshalError::BufferRemaining));
+        let offset = params_start + params_len - rsp_buf.len();
+        return Err(context(code, Part::ResponseParams, offset)(
+            UnmarshalError::BufferRemaining,
+        ));
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{
+        commands::{test::Fuzzer, ContextSave, GetCapability, GetRandom},
+        error::{AuthError, ErrorKind},
+        test::{Respond, ScriptTpm},
+        types::PasswordAuth,
+        TpmExt,
+    };
+
+    const PASSWORD_SESSION: [u8; 5] = [0, 0, 1, 0, 0];
+
+    /// Fake TPM always returning a response with `tag` and `body`
+    fn response(tag: tpm::ST, body: &[&[u8]]) -> ScriptTpm<impl Respond> {
+        let mut tpm = ScriptTpm::raw(|_: &[u8], _: &mut Vec<u8>| {});
+        let size = ResponseHeader::SIZE + body.iter().map(|b| b.len()).sum::<usize>();
+        tpm.rsp.extend_from_slice(&(tag as u16).to_be_bytes());
+        tpm.rsp.extend_from_slice(&(size as u32).to_be_bytes());
+        tpm.rsp.extend_from_slice(&0u32.to_be_bytes());
+        body.iter().for_each(|b| tpm.rsp.extend_from_slice(b));
+        tpm
+    }
+
+    fn unmarshal_error(e: Error) -> UnmarshalError {
+        match e.into_kind() {
+            ErrorKind::Unmarshal(e) => e,
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn invalid_responses() {
+        let cmd = GetRandom { bytes_requested: 2 };
+        let auth = PasswordAuth(&[]);
+
+        let mut tpm = response(tpm::ST::NoSessions, &[&[0, 2, 7, 8]]);
+        assert_eq!(tpm.run(cmd).unwrap().random_bytes, [7, 8]);
+
+        tpm.rsp.pop();
+        let e = unmarshal_error(tpm.run(cmd).unwrap_err());
+        assert!(matches!(e, UnmarshalError::SizeMismatch));
+
+        let mut tpm = response(tpm::ST::Sessions, &[&[0, 2, 7, 8]]);
+        let e = unmarshal_error(tpm.run(cmd).unwrap_err());
+        assert!(matches!(e, UnmarshalError::TagMismatch));
+
+        let body = [0, 0, 0, 4, 0, 2, 7, 8];
+        let mut tpm = response(tpm::ST::Sessions, &[&body, &PASSWORD_SESSION]);
+        assert_eq!(tpm.run(cmd.with_auth(&auth)).unwrap().random_bytes, [7, 8]);
+
+        let body = [0, 0, 0, 9, 0, 2, 7, 8];
+        let mut tpm = response(tpm::ST::Sessions, &[&body, &PASSWORD_SESSION]);
+        let e = unmarshal_error(tpm.run(cmd.with_auth(&auth)).unwrap_err());
+        assert!(matches!(e, UnmarshalError::BufferOverflow));
+
+        let body = [0, 0, 0, 4, 0, 2, 7, 8];
+        let mut tpm = response(tpm::ST::Sessions, &[&body, &PASSWORD_SESSION, &[0]]);
+        let e = unmarshal_error(tpm.run(cmd.with_auth(&auth)).unwrap_err());
+        assert!(matches!(e, UnmarshalError::BufferRemaining));
+
+        let session = [0, 0, 1, 0, 1, 0xAA];
+        let mut tpm = response(tpm::ST::Sessions, &[&body, &session]);
+        let e = tpm.run(cmd.with_auth(&auth)).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Auth(AuthError::InvalidHmac)));
+        assert_eq!(e.context().unwrap().part, Some(Part::ResponseAuths));
+    }
+
+    /// A vendor command, implemented using only the public API
+    #[derive(Clone, Copy, Debug)]
+    struct VendorEcho {
+        value: u32,
+    }
+
+    #[derive(Clone, Copy, Default, Debug)]
+    struct VendorEchoResponse {
+        value: u32,
+    }
+
+    impl CommandData for VendorEcho {
+        fn marshal_params(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+            self.value.marshal(buf)
+        }
+    }
+
+    impl ResponseData<'_> for VendorEchoResponse {
+        fn unmarshal_params(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
+            self.value.unmarshal(buf)
+        }
+    }
+
+    impl Command for VendorEcho {
+        const CODE: tpm::CC = tpm::CC::new(0x2000_0001);
+        type Response<'a> = VendorEchoResponse;
+    }
+
+    impl Auths<0> for VendorEcho {}
+
+    #[test]
+    fn vendor_command() {
+        let cmd = VendorEcho { value: 7 };
+        let mut tpm = response(tpm::ST::NoSessions, &[&[0, 0, 0, 7]]);
+        assert_eq!(tpm.run(cmd).unwrap().value, 7);
+        assert_eq!(tpm.cmd[6..14], [0x20, 0, 0, 1, 0, 0, 0, 7]);
+
+        tpm.rsp[6..10].copy_from_slice(&tpm::rc::FAILURE.to_be_bytes());
+        tpm.rsp.truncate(10);
+        let e = tpm.run(cmd).unwrap_err();
+        assert_eq!(e.context().unwrap().command, VendorEcho::CODE);
+        assert_eq!(std::format!("{:?}", VendorEcho::CODE), "CC(0x20000001)");
+    }
+
+    #[test]
+    fn raw_commands() {
+        let mut tpm = response(tpm::ST::NoSessions, &[]);
+        tpm.rsp[6..10].copy_from_slice(&tpm::rc::FAILURE.to_be_bytes());
+        let cmd = [0x80, 0x01, 0, 0, 0, 0x0C, 0, 0, 0x01, 0x7B, 0, 8];
+        let rsp = tpm.run_raw(&cmd).unwrap();
+        assert_eq!(rsp, [0x80, 0x01, 0, 0, 0, 0x0A, 0, 0, 0x01, 0x01]);
+        assert_eq!(tpm.cmd[..cmd.len()], cmd);
+
+        let e = tpm.run_raw(&cmd[..11]).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            ErrorKind::Marshal(MarshalError::SizeMismatch)
+        ));
+        assert_eq!(e.context().unwrap().command, tpm::CC::GetRandom);
+        let e = tpm.run_raw(&cmd[..4]).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            ErrorKind::Marshal(MarshalError::SizeMismatch)
+        ));
+
+        let mut long = std::vec![0; tpm.cmd.len() + 1];
+        let size = long.len() as u32;
+        long[2..6].copy_from_slice(&size.to_be_bytes());
+        let e = tpm.run_raw(&long).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            ErrorKind::Marshal(MarshalError::BufferOverflow)
+        ));
+
+        tpm.rsp.pop();
+        let e = unmarshal_error(tpm.run_raw(&cmd).unwrap_err());
+        assert!(matches!(e, UnmarshalError::SizeMismatch));
+    }
+
+    #[test]
+    fn parsed_commands() {
+        let cmd = GetRandom { bytes_requested: 2 };
+        let auth = PasswordAuth(&[]);
+
+        // Trailing parameters are rejected by run, but not by run_parsed
+        let mut tpm = response(tpm::ST::NoSessions, &[&[0, 2, 7, 8, 9]]);
+        assert!(tpm.run(cmd).is_err());
+        let rsp = tpm.run_parsed(cmd).unwrap();
+        assert_eq!(rsp[10..], [0, 2, 7, 8, 9]);
+
+        let body = [0, 0, 0, 5, 0, 2, 7, 8, 9];
+        let mut tpm = response(tpm::ST::Sessions, &[&body, &PASSWORD_SESSION]);
+        let rsp = tpm.run_parsed(cmd.with_auth(&auth)).unwrap();
+        assert_eq!(rsp[10..19], body);
+
+        let session = [0, 0, 1, 0, 1, 0xAA];
+        let mut tpm = response(tpm::ST::Sessions, &[&body, &session]);
+        let e = tpm.run_parsed(cmd.with_auth(&auth)).unwrap_err();
+        assert!(matches!(e.kind(), ErrorKind::Auth(AuthError::InvalidHmac)));
+
+        let mut tpm = response(tpm::ST::NoSessions, &[]);
+        tpm.rsp[6..10].copy_from_slice(&tpm::rc::FAILURE.to_be_bytes());
+        assert!(tpm.run_parsed(cmd).unwrap_err().tpm_error().is_some());
+    }
+
+    #[test]
+    fn nested_field_errors() {
+        let cmd = ContextSave {
+            save_handle: 0x80000000,
+        };
+        // TPMS_CONTEXT with a contextBlob claiming 16 bytes, but only 2 present
+        let body = [
+            0, 0, 0, 0, 0, 0, 0, 1, 0x80, 0, 0, 0, 0x40, 0, 0, 1, 0, 16, 1, 2,
+        ];
+        let mut tpm = response(tpm::ST::NoSessions, &[&body]);
+        let e = tpm.run(cmd).unwrap_err();
+        let ctx = *e.context().unwrap();
+        assert_eq!(ctx.field, Some("tpms::Context.context_blob"));
+        assert_eq!(ctx.part, Some(Part::ResponseParams));
+        assert_eq!(
+            std::format!("{e}"),
+            "response is truncated, in ContextSave response parameters at byte 28 \
+             (tpms::Context.context_blob)"
+        );
+        assert!(matches!(unmarshal_error(e), UnmarshalError::BufferOverflow));
+    }
+
+    #[test]
+    fn marshal_error_offsets() {
+        // Room for the header and one byte of the two byte parameter
+        let mut buf = [0; 11];
+        let cmd = GetRandom { bytes_requested: 2 };
+        let e = marshal_command(&mut buf, &[], &cmd, tpm::CC::GetRandom).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            ErrorKind::Marshal(MarshalError::BufferOverflow)
+        ));
+        let ctx = *e.context().unwrap();
+        assert_eq!(ctx.part, Some(Part::CommandParams));
+        assert_eq!(ctx.offset, Some(10));
+        assert_eq!(ctx.field, Some("GetRandom.bytes_requested"));
+    }
+
+    #[test]
+    fn fuzz_run() {
+        let mut fuzzer = Fuzzer::new();
+        let auth = PasswordAuth(&[]);
+        let mut storage = [0u8; 54];
+        for _ in 0..20_000 {
+            let body = &*fuzzer.fill(&mut storage);
+            for tag in [tpm::ST::NoSessions, tpm::ST::Sessions] {
+                let mut tpm = response(tag, &[body]);
+                let cmd = GetRandom { bytes_requested: 8 };
+                let _ = tpm.run(cmd);
+                let _ = tpm.run(cmd.with_auth(&auth));
+                let _ = tpm.run(GetCapability {
+                    capability: tpm::Cap::Algs,
+                    property: 0,
+                    property_count: 8,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Asynchronous versions of [`Tpm`](crate::Tpm) and [`TpmRun`](crate::TpmRun)
+
+use core::future::Future;
+
+use crate::{
+    error::{Context, DriverError, Error},
+    run::{check_response_header, marshal_command, unmarshal_response},
+    Auths, Command,
+};
+
+/// An asynchronous TPM2 Device
+///
+/// This mirrors [`Tpm`](crate::Tpm), except that executing a command returns
+/// a future. Implementations for Tokio are in [`os::tokio`](crate::os::tokio).
+pub trait AsyncTpm: Send {
+    fn command_buf(&mut self) -> &mut [u8];
+    fn response_buf(&self) -> &[u8];
+    fn execute_command(
+        &mut self,
+        cmd_size: u32,
+    ) -> impl Future<Output = Result<(), DriverError>> + Send;
+}
+
+/// Trait extending [`AsyncTpm`] for running raw commands.
+///
+/// As with [`TpmRun`](crate::TpmRun), the response borrows from the TPM's
+/// response buffer. Unlike [`TpmRun`](crate::TpmRun), commands are not
+/// retried, as [`Tpm::should_retry`](crate::Tpm::should_retry) is not
+/// available here.
+#[doc(cfg(feature = "tokio"))]
+pub trait AsyncTpmRun: AsyncTpm {
+    fn run<C: Command + Auths<N> + Send, const N: usize>(
+        &mut self,
+        cmd: C,
+    ) -> impl Future<Output = Result<C::Response<'_>, Error>> + Send;
+}
+
+impl<T: AsyncTpm> AsyncTpmRun for T {
+    async fn run<C: Command + Auths<N> + Send, const N: usize>(
+        &mut self,
+        cmd: C,
+    ) -> Result<C::Response<'_>, Error> {
+        let auths = cmd.auths();
+        let cmd_header = marshal_command(self.command_buf(), &auths, cmd.data(), C::CODE)?;
+        self.execute_command(cmd_header.size)
+            .await
+            .map_err(|e| Error::from(e).with_context(Context::for_command(C::CODE)))?;
+        check_response_header(self.response_buf(), &cmd_header)?;
+
+        let mut rsp = C::Response::default();
+        unmarshal_response(self.response_buf(), &auths, &mut rsp, C::CODE)?;
+        Ok(rsp)
+    }
+}
@@ -1,7 +1,11 @@
 use super::{tpm, tpma, tpms, Handle};
-use crate::Error;
+use crate::{error::AuthError, Error};
 
-pub trait Auth: core::fmt::Debug {
+/// An authorization for a handle in a command
+///
+/// Auths must be [`Sync`], so that commands can be sent to another thread
+/// (e.g. when running commands asynchronously).
+pub trait Auth: core::fmt::Debug + Sync {
     fn get_auth(&self) -> tpms::AuthCommand;
     fn set_auth(&self, auth: &tpms::AuthResponse) -> Result<(), Error>;
 }
@@ -26,9 +30,15 @@ impl Auth for PasswordAuth<'_> {
     }
 
     fn set_auth(&self, auth: &tpms::AuthResponse) -> Result<(), Error> {
-        assert!(auth.nonce.is_empty());
-        assert_eq!(auth.session_attributes, tpma::Session::CONTINUE_SESSION);
-        assert!(auth.hmac.is_empty());
+        if !auth.nonce.is_empty() {
+            return Err(AuthError::InvalidNonce.into());
+        }
+        if auth.session_attributes != tpma::Session::CONTINUE_SESSION {
+            return Err(AuthError::InvalidAttributes.into());
+        }
+        if !auth.hmac.is_empty() {
+            return Err(AuthError::InvalidHmac.into());
+        }
         Ok(())
     }
 }
@@ -40,6 +40,9 @@ pub(crate) struct ResponseHeader {
     pub size: u32,
     pub code: tpm::RC,
 }
+impl ResponseHeader {
+    pub const SIZE: usize = 10;
+}
 impl Unmarshal<'_> for ResponseHeader {
     fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
         self.tag.unmarshal(buf)?;
@@ -69,6 +72,6 @@ mod test {
     #[test]
     fn header_size() {
         assert_eq!(CommandHeader::SIZE, 10);
-        // assert_eq!(ResponseHeader::SIZE, 10);
+        assert_eq!(ResponseHeader::SIZE, 10);
     }
 }
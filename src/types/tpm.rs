@@ -4,8 +4,9 @@
 //! TODO explain sections (6, 7, 13, 16)
 //! TODO Move Handle here?
 
-use core::num::NonZeroU32;
+use core::{fmt, num::NonZeroU32};
 
+use super::tpma;
 use crate::{
     error::{TpmError, UnmarshalError},
     MarshalFixed, Unmarshal, UnmarshalFixed,
@@ -14,144 +15,496 @@ use crate::{
 /// TPM_RH constants
 pub mod rh {
     use crate::types::Handle;
+    pub const OWNER: Handle = 0x40000001;
+    pub const NULL: Handle = 0x40000007;
     pub const PASSWORD: Handle = 0x40000009;
+    pub const LOCKOUT: Handle = 0x4000000A;
+    pub const ENDORSEMENT: Handle = 0x4000000B;
+    pub const PLATFORM: Handle = 0x4000000C;
+    pub const PLATFORM_NV: Handle = 0x4000000D;
+}
+
+/// TPM_HC constants
+pub mod hc {
+    use crate::types::{tpms::MIN_PCRS, Handle};
+    pub const PCR_FIRST: Handle = 0x00000000;
+    pub const PCR_LAST: Handle = PCR_FIRST + MIN_PCRS as Handle - 1;
+    pub const NV_INDEX_FIRST: Handle = 0x01000000;
+    pub const NV_INDEX_LAST: Handle = 0x01FFFFFF;
+
+    /// Returns the handle of PCR `index`, or `None` if there is no such PCR.
+    ///
+    /// The returned handle can be converted to an
+    /// [`AuthHandle`](crate::types::AuthHandle) for use with commands like
+    /// [`PcrExtend`](crate::commands::PcrExtend).
+    pub const fn pcr(index: usize) -> Option<Handle> {
+        if index < MIN_PCRS {
+            Some(PCR_FIRST + index as Handle)
+        } else {
+            None
+        }
+    }
+}
+
+/// TPM_HT constants
+///
+/// The handle type is the most significant byte of a handle.
+pub mod ht {
+    use crate::types::Handle;
+    pub const PCR: u8 = 0x00;
+    pub const NV_INDEX: u8 = 0x01;
+    pub const HMAC_SESSION: u8 = 0x02;
+    pub const LOADED_SESSION: u8 = 0x02;
+    pub const POLICY_SESSION: u8 = 0x03;
+    pub const SAVED_SESSION: u8 = 0x03;
+    pub const PERMANENT: u8 = 0x40;
+    pub const TRANSIENT: u8 = 0x80;
+    pub const PERSISTENT: u8 = 0x81;
+    pub const AC: u8 = 0x90;
+
+    /// Returns the type of `handle`.
+    pub const fn of(handle: Handle) -> u8 {
+        (handle >> 24) as u8
+    }
 }
 
 // 5.3 Miscellaneous Types
 /// TPM_KEY_BITS
 pub type KeyBits = u16;
 
+// Flags used in the table below
+const NV: u32 = tpma::Cc::NV.bits();
+const EXTENSIVE: u32 = tpma::Cc::EXTENSIVE.bits();
+const FLUSHED: u32 = tpma::Cc::FLUSHED.bits();
+const R_HANDLE: u32 = tpma::Cc::R_HANDLE.bits();
+
+macro_rules! commands {
+    ($($name:ident = $code:literal => ($flags:expr, $c_handles:literal, $auths:literal),)+) => {
+        #[allow(non_upper_case_globals)]
+        impl CC {
+            $(pub const $name: CC = CC($code);)+
+
+            /// Every command code defined in the TPM2 spec
+            pub const ALL: &'static [CC] = &[$(CC::$name,)+];
+
+            /// The name of the command (e.g. `"GetRandom"`), or `None` if it
+            /// is not defined in the TPM2 spec
+            pub const fn name(self) -> Option<&'static str> {
+                match self {
+                    $(CC::$name => Some(stringify!($name)),)+
+                    _ => None,
+                }
+            }
+
+            /// The TPMA_CC flags, number of command handles, and number of
+            /// handles requiring authorization
+            const fn info(self) -> Option<(u32, u32, usize)> {
+                match self {
+                    $(CC::$name => Some(($flags, $c_handles, $auths)),)+
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
 /// TPM_CC values
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-#[non_exhaustive]
-#[repr(u32)]
-pub enum CC {
-    NvUndefineSpaceSpecial = 0x0000011f,
-    EvictControl = 0x00000120,
-    HierarchyControl = 0x00000121,
-    NvUndefineSpace = 0x00000122,
-    ChangeEps = 0x00000124,
-    ChangePps = 0x00000125,
-    Clear = 0x00000126,
-    ClearControl = 0x00000127,
-    ClockSet = 0x00000128,
-    HierarchyChangeAuth = 0x00000129,
-    NvDefineSpace = 0x0000012a,
-    PcrAllocate = 0x0000012b,
-    PcrSetAuthPolicy = 0x0000012c,
-    PpCommands = 0x0000012d,
-    SetPrimaryPolicy = 0x0000012e,
-    FieldUpgradeStart = 0x0000012f,
-    ClockRateAdjust = 0x00000130,
-    CreatePrimary = 0x00000131,
-    NvGlobalWriteLock = 0x00000132,
-    GetCommandAuditDigest = 0x00000133,
-    NvIncrement = 0x00000134,
-    NvSetBits = 0x00000135,
-    NvExtend = 0x00000136,
-    NvWrite = 0x00000137,
-    NvWriteLock = 0x00000138,
-    DictionaryAttackLockReset = 0x00000139,
-    DictionaryAttackParameters = 0x0000013a,
-    NvChangeAuth = 0x0000013b,
-    PcrEvent = 0x0000013c,
-    PcrReset = 0x0000013d,
-    SequenceComplete = 0x0000013e,
-    SetAlgorithmSet = 0x0000013f,
-    SetCommandCodeAuditStatus = 0x00000140,
-    FieldUpgradeData = 0x00000141,
-    IncrementalSelfTest = 0x00000142,
-    SelfTest = 0x00000143,
-    Startup = 0x00000144,
-    Shutdown = 0x00000145,
-    StirRandom = 0x00000146,
-    ActivateCredential = 0x00000147,
-    Certify = 0x00000148,
-    PolicyNv = 0x00000149,
-    CertifyCreation = 0x0000014a,
-    Duplicate = 0x0000014b,
-    GetTime = 0x0000014c,
-    GetSessionAuditDigest = 0x0000014d,
-    NvRead = 0x0000014e,
-    NvReadLock = 0x0000014f,
-    ObjectChangeAuth = 0x00000150,
-    PolicySecret = 0x00000151,
-    Rewrap = 0x00000152,
-    Create = 0x00000153,
-    EcdhZGen = 0x00000154,
-    Mac = 0x00000155,
-    Import = 0x00000156,
-    Load = 0x00000157,
-    Quote = 0x00000158,
-    RsaDecrypt = 0x00000159,
-    MacStart = 0x0000015b,
-    SequenceUpdate = 0x0000015c,
-    Sign = 0x0000015d,
-    Unseal = 0x0000015e,
-    PolicySigned = 0x00000160,
-    ContextLoad = 0x00000161,
-    ContextSave = 0x00000162,
-    EcdhKeyGen = 0x00000163,
-    EncryptDecrypt = 0x00000164,
-    FlushContext = 0x00000165,
-    LoadExternal = 0x00000167,
-    MakeCredential = 0x00000168,
-    NvReadPublic = 0x00000169,
-    PolicyAuthorize = 0x0000016a,
-    PolicyAuthValue = 0x0000016b,
-    PolicyCommandCode = 0x0000016c,
-    PolicyCounterTimer = 0x0000016d,
-    PolicyCpHash = 0x0000016e,
-    PolicyLocality = 0x0000016f,
-    PolicyNameHash = 0x00000170,
-    PolicyOR = 0x00000171,
-    PolicyTicket = 0x00000172,
-    ReadPublic = 0x00000173,
-    RsaEncrypt = 0x00000174,
-    StartAuthSession = 0x00000176,
-    VerifySignature = 0x00000177,
-    EccParameters = 0x00000178,
-    FirmwareRead = 0x00000179,
-    GetCapability = 0x0000017a,
-    GetRandom = 0x0000017b,
-    GetTestResult = 0x0000017c,
-    Hash = 0x0000017d,
-    PcrRead = 0x0000017e,
-    PolicyPcr = 0x0000017f,
-    PolicyRestart = 0x00000180,
-    ReadClock = 0x00000181,
-    PcrExtend = 0x00000182,
-    PcrSetAuthValue = 0x00000183,
-    NvCertify = 0x00000184,
-    EventSequenceComplete = 0x00000185,
-    HashSequenceStart = 0x00000186,
-    PolicyPhysicalPresence = 0x00000187,
-    PolicyDuplicationSelect = 0x00000188,
-    PolicyGetDigest = 0x00000189,
-    TestParms = 0x0000018a,
-    Commit = 0x0000018b,
-    PolicyPassword = 0x0000018c,
-    ZGen2Phase = 0x0000018d,
-    EcEphemeral = 0x0000018e,
-    PolicyNvWritten = 0x0000018f,
-    PolicyTemplate = 0x00000190,
-    CreateLoaded = 0x00000191,
-    PolicyAuthorizeNv = 0x00000192,
-    EncryptDecrypt2 = 0x00000193,
-    AcGetCapability = 0x00000194,
-    AcSend = 0x00000195,
-    PolicyAcSendSelect = 0x00000196,
-    CertifyX509 = 0x00000197,
-    ActSetTimeout = 0x00000198,
+///
+/// Constants are provided for the commands defined in the TPM2 spec, but any
+/// code can be used, such as for vendor-specific commands (see
+/// [`CC::is_vendor`]) or commands from newer versions of the spec. See
+/// [`CC::attributes`] for the properties of each command.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct CC(u32);
+
+// From the "TPM2_* Command" tables in Part 3 of the TPM2 Spec
+commands! {
+    NvUndefineSpaceSpecial = 0x0000011f => (NV, 2, 2),
+    EvictControl = 0x00000120 => (NV, 2, 1),
+    HierarchyControl = 0x00000121 => (NV | EXTENSIVE, 1, 1),
+    NvUndefineSpace = 0x00000122 => (NV, 2, 1),
+    ChangeEps = 0x00000124 => (NV | EXTENSIVE, 1, 1),
+    ChangePps = 0x00000125 => (NV | EXTENSIVE, 1, 1),
+    Clear = 0x00000126 => (NV | EXTENSIVE, 1, 1),
+    ClearControl = 0x00000127 => (NV, 1, 1),
+    ClockSet = 0x00000128 => (NV, 1, 1),
+    HierarchyChangeAuth = 0x00000129 => (NV, 1, 1),
+    NvDefineSpace = 0x0000012a => (NV, 1, 1),
+    PcrAllocate = 0x0000012b => (NV, 1, 1),
+    PcrSetAuthPolicy = 0x0000012c => (NV, 1, 1),
+    PpCommands = 0x0000012d => (NV, 1, 1),
+    SetPrimaryPolicy = 0x0000012e => (NV, 1, 1),
+    FieldUpgradeStart = 0x0000012f => (0, 2, 1),
+    ClockRateAdjust = 0x00000130 => (0, 1, 1),
+    CreatePrimary = 0x00000131 => (R_HANDLE, 1, 1),
+    NvGlobalWriteLock = 0x00000132 => (NV, 1, 1),
+    GetCommandAuditDigest = 0x00000133 => (NV, 2, 2),
+    NvIncrement = 0x00000134 => (NV, 2, 1),
+    NvSetBits = 0x00000135 => (NV, 2, 1),
+    NvExtend = 0x00000136 => (NV, 2, 1),
+    NvWrite = 0x00000137 => (NV, 2, 1),
+    NvWriteLock = 0x00000138 => (NV, 2, 1),
+    DictionaryAttackLockReset = 0x00000139 => (NV, 1, 1),
+    DictionaryAttackParameters = 0x0000013a => (NV, 1, 1),
+    NvChangeAuth = 0x0000013b => (NV, 1, 1),
+    PcrEvent = 0x0000013c => (NV, 1, 1),
+    PcrReset = 0x0000013d => (NV, 1, 1),
+    SequenceComplete = 0x0000013e => (FLUSHED, 1, 1),
+    SetAlgorithmSet = 0x0000013f => (NV, 1, 1),
+    SetCommandCodeAuditStatus = 0x00000140 => (NV, 1, 1),
+    FieldUpgradeData = 0x00000141 => (NV, 0, 0),
+    IncrementalSelfTest = 0x00000142 => (NV, 0, 0),
+    SelfTest = 0x00000143 => (NV, 0, 0),
+    Startup = 0x00000144 => (NV, 0, 0),
+    Shutdown = 0x00000145 => (NV, 0, 0),
+    StirRandom = 0x00000146 => (NV, 0, 0),
+    ActivateCredential = 0x00000147 => (0, 2, 2),
+    Certify = 0x00000148 => (0, 2, 2),
+    PolicyNv = 0x00000149 => (0, 3, 1),
+    CertifyCreation = 0x0000014a => (0, 2, 1),
+    Duplicate = 0x0000014b => (0, 2, 1),
+    GetTime = 0x0000014c => (0, 2, 2),
+    GetSessionAuditDigest = 0x0000014d => (0, 3, 2),
+    NvRead = 0x0000014e => (0, 2, 1),
+    NvReadLock = 0x0000014f => (NV, 2, 1),
+    ObjectChangeAuth = 0x00000150 => (0, 2, 1),
+    PolicySecret = 0x00000151 => (0, 2, 1),
+    Rewrap = 0x00000152 => (0, 2, 1),
+    Create = 0x00000153 => (0, 1, 1),
+    EcdhZGen = 0x00000154 => (0, 1, 1),
+    Mac = 0x00000155 => (0, 1, 1),
+    Import = 0x00000156 => (0, 1, 1),
+    Load = 0x00000157 => (R_HANDLE, 1, 1),
+    Quote = 0x00000158 => (0, 1, 1),
+    RsaDecrypt = 0x00000159 => (0, 1, 1),
+    MacStart = 0x0000015b => (R_HANDLE, 1, 1),
+    SequenceUpdate = 0x0000015c => (0, 1, 1),
+    Sign = 0x0000015d => (0, 1, 1),
+    Unseal = 0x0000015e => (0, 1, 1),
+    PolicySigned = 0x00000160 => (0, 2, 0),
+    ContextLoad = 0x00000161 => (R_HANDLE, 0, 0),
+    ContextSave = 0x00000162 => (0, 1, 0),
+    EcdhKeyGen = 0x00000163 => (0, 1, 0),
+    EncryptDecrypt = 0x00000164 => (0, 1, 1),
+    FlushContext = 0x00000165 => (FLUSHED, 0, 0),
+    LoadExternal = 0x00000167 => (R_HANDLE, 0, 0),
+    MakeCredential = 0x00000168 => (0, 1, 0),
+    NvReadPublic = 0x00000169 => (0, 1, 0),
+    PolicyAuthorize = 0x0000016a => (0, 1, 0),
+    PolicyAuthValue = 0x0000016b => (0, 1, 0),
+    PolicyCommandCode = 0x0000016c => (0, 1, 0),
+    PolicyCounterTimer = 0x0000016d => (0, 1, 0),
+    PolicyCpHash = 0x0000016e => (0, 1, 0),
+    PolicyLocality = 0x0000016f => (0, 1, 0),
+    PolicyNameHash = 0x00000170 => (0, 1, 0),
+    PolicyOR = 0x00000171 => (0, 1, 0),
+    PolicyTicket = 0x00000172 => (0, 1, 0),
+    ReadPublic = 0x00000173 => (0, 1, 0),
+    RsaEncrypt = 0x00000174 => (0, 1, 0),
+    StartAuthSession = 0x00000176 => (R_HANDLE, 2, 0),
+    VerifySignature = 0x00000177 => (0, 1, 0),
+    EccParameters = 0x00000178 => (0, 0, 0),
+    FirmwareRead = 0x00000179 => (0, 0, 0),
+    GetCapability = 0x0000017a => (0, 0, 0),
+    GetRandom = 0x0000017b => (0, 0, 0),
+    GetTestResult = 0x0000017c => (0, 0, 0),
+    Hash = 0x0000017d => (0, 0, 0),
+    PcrRead = 0x0000017e => (0, 0, 0),
+    PolicyPcr = 0x0000017f => (0, 1, 0),
+    PolicyRestart = 0x00000180 => (0, 1, 0),
+    ReadClock = 0x00000181 => (0, 0, 0),
+    PcrExtend = 0x00000182 => (NV, 1, 1),
+    PcrSetAuthValue = 0x00000183 => (0, 1, 1),
+    NvCertify = 0x00000184 => (0, 3, 2),
+    EventSequenceComplete = 0x00000185 => (NV | FLUSHED, 2, 2),
+    HashSequenceStart = 0x00000186 => (R_HANDLE, 0, 0),
+    PolicyPhysicalPresence = 0x00000187 => (0, 1, 0),
+    PolicyDuplicationSelect = 0x00000188 => (0, 1, 0),
+    PolicyGetDigest = 0x00000189 => (0, 1, 0),
+    TestParms = 0x0000018a => (0, 0, 0),
+    Commit = 0x0000018b => (0, 1, 1),
+    PolicyPassword = 0x0000018c => (0, 1, 0),
+    ZGen2Phase = 0x0000018d => (0, 1, 1),
+    EcEphemeral = 0x0000018e => (0, 0, 0),
+    PolicyNvWritten = 0x0000018f => (0, 1, 0),
+    PolicyTemplate = 0x00000190 => (0, 1, 0),
+    CreateLoaded = 0x00000191 => (R_HANDLE, 1, 1),
+    PolicyAuthorizeNv = 0x00000192 => (0, 3, 1),
+    EncryptDecrypt2 = 0x00000193 => (0, 1, 1),
+    AcGetCapability = 0x00000194 => (0, 1, 0),
+    AcSend = 0x00000195 => (0, 3, 2),
+    PolicyAcSendSelect = 0x00000196 => (0, 1, 0),
+    CertifyX509 = 0x00000197 => (0, 2, 2),
+    ActSetTimeout = 0x00000198 => (0, 1, 1),
+}
+
+impl CC {
+    pub const fn new(code: u32) -> Self {
+        Self(code)
+    }
+
+    /// The raw TPM_CC value
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if this is a vendor-specific command (TPM_CC_V is set).
+    pub const fn is_vendor(self) -> bool {
+        self.0 & tpma::Cc::V.bits() != 0
+    }
+
+    /// The attributes of this command (as a TPM would report them for
+    /// [`Cap::Commands`]), giving the number of command and response handles
+    /// and whether the command modifies NV memory or flushes contexts.
+    ///
+    /// Returns `None` for commands not defined in the TPM2 spec.
+    pub const fn attributes(self) -> Option<tpma::Cc> {
+        let Some((flags, c_handles, _)) = self.info() else {
+            return None;
+        };
+        let c_handles = c_handles << tpma::Cc::C_HANDLES.bits().trailing_zeros();
+        Some(tpma::Cc::from_bits_truncate(self.0 | flags | c_handles))
+    }
+
+    /// The number of handles in the command which require authorization
+    ///
+    /// These are the first handles in the command, and each requires a
+    /// session (see [`Auths`](crate::Auths)). Returns `None` for commands not
+    /// defined in the TPM2 spec.
+    pub const fn auth_handles(self) -> Option<usize> {
+        match self.info() {
+            Some((_, _, auths)) => Some(auths),
+            None => None,
+        }
+    }
+}
+
+impl fmt::Debug for CC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "CC({:#010X})", self.0),
+        }
+    }
+}
+
+impl From<u32> for CC {
+    fn from(code: u32) -> Self {
+        Self(code)
+    }
+}
+
+impl From<CC> for u32 {
+    fn from(cc: CC) -> Self {
+        cc.0
+    }
 }
 
 impl MarshalFixed for CC {
+    const SIZE: usize = <u32 as MarshalFixed>::SIZE;
+    type ARRAY = [u8; Self::SIZE];
+    fn marshal_fixed(&self, arr: &mut Self::ARRAY) {
+        self.0.marshal_fixed(arr)
+    }
+}
+
+impl UnmarshalFixed for CC {
+    fn unmarshal_fixed(arr: &Self::ARRAY) -> Self {
+        Self(u32::unmarshal_fixed(arr))
+    }
+}
+
+/// TPM_CAP values
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum Cap {
+    #[default]
+    Algs = 0x00000000,
+    Handles = 0x00000001,
+    Commands = 0x00000002,
+    PpCommands = 0x00000003,
+    AuditCommands = 0x00000004,
+    Pcrs = 0x00000005,
+    TpmProperties = 0x00000006,
+    PcrProperties = 0x00000007,
+    EccCurves = 0x00000008,
+    AuthPolicies = 0x00000009,
+    Act = 0x0000000A,
+    VendorProperty = 0x00000100,
+}
+impl MarshalFixed for Cap {
     const SIZE: usize = <u32 as MarshalFixed>::SIZE;
     type ARRAY = [u8; Self::SIZE];
     fn marshal_fixed(&self, arr: &mut Self::ARRAY) {
         (*self as u32).marshal_fixed(arr)
     }
 }
+impl Unmarshal<'_> for Cap {
+    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
+        *self = match u32::unmarshal_val(buf)? {
+            0x00000000 => Self::Algs,
+            0x00000001 => Self::Handles,
+            0x00000002 => Self::Commands,
+            0x00000003 => Self::PpCommands,
+            0x00000004 => Self::AuditCommands,
+            0x00000005 => Self::Pcrs,
+            0x00000006 => Self::TpmProperties,
+            0x00000007 => Self::PcrProperties,
+            0x00000008 => Self::EccCurves,
+            0x00000009 => Self::AuthPolicies,
+            0x0000000A => Self::Act,
+            0x00000100 => Self::VendorProperty,
+            _ => return Err(UnmarshalError::InvalidValue),
+        };
+        Ok(())
+    }
+}
+
+/// TPM_CLOCK_ADJUST values
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[repr(i8)]
+pub enum ClockAdjust {
+    CoarseSlower = -3,
+    MediumSlower = -2,
+    FineSlower = -1,
+    #[default]
+    NoChange = 0,
+    FineFaster = 1,
+    MediumFaster = 2,
+    CoarseFaster = 3,
+}
+impl MarshalFixed for ClockAdjust {
+    const SIZE: usize = <u8 as MarshalFixed>::SIZE;
+    type ARRAY = [u8; Self::SIZE];
+    fn marshal_fixed(&self, arr: &mut Self::ARRAY) {
+        *arr = (*self as i8).to_be_bytes();
+    }
+}
+
+/// TPM_NT values
+///
+/// The type of an NV Index, stored in the [`tpma::Nv::TPM_NT`] field.
+///
+/// [`tpma::Nv::TPM_NT`]: crate::types::tpma::Nv::TPM_NT
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum NT {
+    #[default]
+    Ordinary = 0x0,
+    Counter = 0x1,
+    Bits = 0x2,
+    Extend = 0x4,
+    PinFail = 0x8,
+    PinPass = 0x9,
+}
+
+/// TPM_PT constants
+///
+/// These are the properties returned by
+/// [`GetCapability`](crate::commands::GetCapability) for
+/// [`Cap::TpmProperties`].
+pub mod pt {
+    pub const NONE: u32 = 0x00000000;
+    pub const GROUP: u32 = 0x00000100;
+    pub const FIXED: u32 = GROUP;
+    pub const FAMILY_INDICATOR: u32 = FIXED;
+    pub const LEVEL: u32 = FIXED + 1;
+    pub const REVISION: u32 = FIXED + 2;
+    pub const DAY_OF_YEAR: u32 = FIXED + 3;
+    pub const YEAR: u32 = FIXED + 4;
+    pub const MANUFACTURER: u32 = FIXED + 5;
+    pub const VENDOR_STRING_1: u32 = FIXED + 6;
+    pub const VENDOR_STRING_2: u32 = FIXED + 7;
+    pub const VENDOR_STRING_3: u32 = FIXED + 8;
+    pub const VENDOR_STRING_4: u32 = FIXED + 9;
+    pub const VENDOR_TPM_TYPE: u32 = FIXED + 10;
+    pub const FIRMWARE_VERSION_1: u32 = FIXED + 11;
+    pub const FIRMWARE_VERSION_2: u32 = FIXED + 12;
+    pub const INPUT_BUFFER: u32 = FIXED + 13;
+    pub const HR_TRANSIENT_MIN: u32 = FIXED + 14;
+    pub const HR_PERSISTENT_MIN: u32 = FIXED + 15;
+    pub const HR_LOADED_MIN: u32 = FIXED + 16;
+    pub const ACTIVE_SESSIONS_MAX: u32 = FIXED + 17;
+    pub const PCR_COUNT: u32 = FIXED + 18;
+    pub const PCR_SELECT_MIN: u32 = FIXED + 19;
+    pub const CONTEXT_GAP_MAX: u32 = FIXED + 20;
+    pub const NV_COUNTERS_MAX: u32 = FIXED + 22;
+    pub const NV_INDEX_MAX: u32 = FIXED + 23;
+    pub const MEMORY: u32 = FIXED + 24;
+    pub const CLOCK_UPDATE: u32 = FIXED + 25;
+    pub const CONTEXT_HASH: u32 = FIXED + 26;
+    pub const CONTEXT_SYM: u32 = FIXED + 27;
+    pub const CONTEXT_SYM_SIZE: u32 = FIXED + 28;
+    pub const ORDERLY_COUNT: u32 = FIXED + 29;
+    pub const MAX_COMMAND_SIZE: u32 = FIXED + 30;
+    pub const MAX_RESPONSE_SIZE: u32 = FIXED + 31;
+    pub const MAX_DIGEST: u32 = FIXED + 32;
+    pub const MAX_OBJECT_CONTEXT: u32 = FIXED + 33;
+    pub const MAX_SESSION_CONTEXT: u32 = FIXED + 34;
+    pub const PS_FAMILY_INDICATOR: u32 = FIXED + 35;
+    pub const PS_LEVEL: u32 = FIXED + 36;
+    pub const PS_REVISION: u32 = FIXED + 37;
+    pub const PS_DAY_OF_YEAR: u32 = FIXED + 38;
+    pub const PS_YEAR: u32 = FIXED + 39;
+    pub const SPLIT_MAX: u32 = FIXED + 40;
+    pub const TOTAL_COMMANDS: u32 = FIXED + 41;
+    pub const LIBRARY_COMMANDS: u32 = FIXED + 42;
+    pub const VENDOR_COMMANDS: u32 = FIXED + 43;
+    pub const NV_BUFFER_MAX: u32 = FIXED + 44;
+    pub const MODES: u32 = FIXED + 45;
+    pub const MAX_CAP_BUFFER: u32 = FIXED + 46;
+    pub const FIRMWARE_SVN: u32 = FIXED + 47;
+    pub const FIRMWARE_MAX_SVN: u32 = FIXED + 48;
+
+    pub const VAR: u32 = GROUP * 2;
+    pub const PERMANENT: u32 = VAR;
+    pub const STARTUP_CLEAR: u32 = VAR + 1;
+    pub const HR_NV_INDEX: u32 = VAR + 2;
+    pub const HR_LOADED: u32 = VAR + 3;
+    pub const HR_LOADED_AVAIL: u32 = VAR + 4;
+    pub const HR_ACTIVE: u32 = VAR + 5;
+    pub const HR_ACTIVE_AVAIL: u32 = VAR + 6;
+    pub const HR_TRANSIENT_AVAIL: u32 = VAR + 7;
+    pub const HR_PERSISTENT: u32 = VAR + 8;
+    pub const HR_PERSISTENT_AVAIL: u32 = VAR + 9;
+    pub const NV_COUNTERS: u32 = VAR + 10;
+    pub const NV_COUNTERS_AVAIL: u32 = VAR + 11;
+    pub const ALGORITHM_SET: u32 = VAR + 12;
+    pub const LOADED_CURVES: u32 = VAR + 13;
+    pub const LOCKOUT_COUNTER: u32 = VAR + 14;
+    pub const MAX_AUTH_FAIL: u32 = VAR + 15;
+    pub const LOCKOUT_INTERVAL: u32 = VAR + 16;
+    pub const LOCKOUT_RECOVERY: u32 = VAR + 17;
+    pub const NV_WRITE_RECOVERY: u32 = VAR + 18;
+    pub const AUDIT_COUNTER_0: u32 = VAR + 19;
+    pub const AUDIT_COUNTER_1: u32 = VAR + 20;
+}
+
+/// TPM_PT_PCR constants
+///
+/// These are the properties returned by
+/// [`GetCapability`](crate::commands::GetCapability) for
+/// [`Cap::PcrProperties`].
+pub mod pt_pcr {
+    pub const SAVE: u32 = 0x00000000;
+    pub const EXTEND_L0: u32 = 0x00000001;
+    pub const RESET_L0: u32 = 0x00000002;
+    pub const EXTEND_L1: u32 = 0x00000003;
+    pub const RESET_L1: u32 = 0x00000004;
+    pub const EXTEND_L2: u32 = 0x00000005;
+    pub const RESET_L2: u32 = 0x00000006;
+    pub const EXTEND_L3: u32 = 0x00000007;
+    pub const RESET_L3: u32 = 0x00000008;
+    pub const EXTEND_L4: u32 = 0x00000009;
+    pub const RESET_L4: u32 = 0x0000000A;
+    pub const NO_INCREMENT: u32 = 0x00000011;
+    pub const DRTM_RESET: u32 = 0x00000012;
+    pub const POLICY: u32 = 0x00000013;
+    pub const AUTH: u32 = 0x00000014;
+}
 
 /// TPM_SU values
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
@@ -173,6 +526,143 @@ impl MarshalFixed for SU {
 /// TPM_RC
 pub type RC = Option<TpmError>;
 
+/// TPM_RC constants
+///
+/// Format-one codes are listed without their parameter, handle or session
+/// number (see [`TpmError::base`](crate::error::TpmError::base)).
+pub mod rc {
+    /// Set for all TPM 2.0 format-zero codes
+    pub const VER1: u32 = 0x100;
+    /// Set for all format-one codes
+    pub const FMT1: u32 = 0x080;
+    /// Set for all warnings (includes [`VER1`])
+    pub const WARN: u32 = 0x900;
+    /// Set for vendor-defined format-zero codes
+    pub const VENDOR: u32 = 0x400;
+    /// In format-one codes, indicates that N is a parameter number
+    pub const P: u32 = 0x040;
+    /// In format-one codes without [`P`], indicates that N is a session number
+    pub const S: u32 = 0x800;
+    /// In format-one codes, the mask for N
+    pub const N_MASK: u32 = 0xF00;
+
+    macro_rules! rc_codes {
+        ($($name:ident = $value:expr,)+) => {
+            $(pub const $name: u32 = $value;)+
+
+            /// Returns the name (e.g. `"TPM_RC_VALUE"`) of a code listed above
+            pub const fn name(rc: u32) -> Option<&'static str> {
+                match rc {
+                    $($name => Some(concat!("TPM_RC_", stringify!($name))),)+
+                    _ => None,
+                }
+            }
+        };
+    }
+
+    rc_codes! {
+        BAD_TAG = 0x01E,
+        INITIALIZE = VER1,
+        FAILURE = VER1 + 0x001,
+        SEQUENCE = VER1 + 0x003,
+        PRIVATE = VER1 + 0x00B,
+        HMAC = VER1 + 0x019,
+        DISABLED = VER1 + 0x020,
+        EXCLUSIVE = VER1 + 0x021,
+        AUTH_TYPE = VER1 + 0x024,
+        AUTH_MISSING = VER1 + 0x025,
+        POLICY = VER1 + 0x026,
+        PCR = VER1 + 0x027,
+        PCR_CHANGED = VER1 + 0x028,
+        UPGRADE = VER1 + 0x02D,
+        TOO_MANY_CONTEXTS = VER1 + 0x02E,
+        AUTH_UNAVAILABLE = VER1 + 0x02F,
+        REBOOT = VER1 + 0x030,
+        UNBALANCED = VER1 + 0x031,
+        COMMAND_SIZE = VER1 + 0x042,
+        COMMAND_CODE = VER1 + 0x043,
+        AUTHSIZE = VER1 + 0x044,
+        AUTH_CONTEXT = VER1 + 0x045,
+        NV_RANGE = VER1 + 0x046,
+        NV_SIZE = VER1 + 0x047,
+        NV_LOCKED = VER1 + 0x048,
+        NV_AUTHORIZATION = VER1 + 0x049,
+        NV_UNINITIALIZED = VER1 + 0x04A,
+        NV_SPACE = VER1 + 0x04B,
+        NV_DEFINED = VER1 + 0x04C,
+        BAD_CONTEXT = VER1 + 0x050,
+        CPHASH = VER1 + 0x051,
+        PARENT = VER1 + 0x052,
+        NEEDS_TEST = VER1 + 0x053,
+        NO_RESULT = VER1 + 0x054,
+        SENSITIVE = VER1 + 0x055,
+        ASYMMETRIC = FMT1 + 0x001,
+        ATTRIBUTES = FMT1 + 0x002,
+        HASH = FMT1 + 0x003,
+        VALUE = FMT1 + 0x004,
+        HIERARCHY = FMT1 + 0x005,
+        KEY_SIZE = FMT1 + 0x007,
+        MGF = FMT1 + 0x008,
+        MODE = FMT1 + 0x009,
+        TYPE = FMT1 + 0x00A,
+        HANDLE = FMT1 + 0x00B,
+        KDF = FMT1 + 0x00C,
+        RANGE = FMT1 + 0x00D,
+        AUTH_FAIL = FMT1 + 0x00E,
+        NONCE = FMT1 + 0x00F,
+        PP = FMT1 + 0x010,
+        SCHEME = FMT1 + 0x012,
+        SIZE = FMT1 + 0x015,
+        SYMMETRIC = FMT1 + 0x016,
+        TAG = FMT1 + 0x017,
+        SELECTOR = FMT1 + 0x018,
+        INSUFFICIENT = FMT1 + 0x01A,
+        SIGNATURE = FMT1 + 0x01B,
+        KEY = FMT1 + 0x01C,
+        POLICY_FAIL = FMT1 + 0x01D,
+        INTEGRITY = FMT1 + 0x01F,
+        TICKET = FMT1 + 0x020,
+        RESERVED_BITS = FMT1 + 0x021,
+        BAD_AUTH = FMT1 + 0x022,
+        EXPIRED = FMT1 + 0x023,
+        POLICY_CC = FMT1 + 0x024,
+        BINDING = FMT1 + 0x025,
+        CURVE = FMT1 + 0x026,
+        ECC_POINT = FMT1 + 0x027,
+        FW_LIMITED = FMT1 + 0x028,
+        SVN_LIMITED = FMT1 + 0x029,
+        CONTEXT_GAP = WARN + 0x001,
+        OBJECT_MEMORY = WARN + 0x002,
+        SESSION_MEMORY = WARN + 0x003,
+        MEMORY = WARN + 0x004,
+        SESSION_HANDLES = WARN + 0x005,
+        OBJECT_HANDLES = WARN + 0x006,
+        LOCALITY = WARN + 0x007,
+        YIELDED = WARN + 0x008,
+        CANCELED = WARN + 0x009,
+        TESTING = WARN + 0x00A,
+        REFERENCE_H0 = WARN + 0x010,
+        REFERENCE_H1 = WARN + 0x011,
+        REFERENCE_H2 = WARN + 0x012,
+        REFERENCE_H3 = WARN + 0x013,
+        REFERENCE_H4 = WARN + 0x014,
+        REFERENCE_H5 = WARN + 0x015,
+        REFERENCE_H6 = WARN + 0x016,
+        REFERENCE_S0 = WARN + 0x018,
+        REFERENCE_S1 = WARN + 0x019,
+        REFERENCE_S2 = WARN + 0x01A,
+        REFERENCE_S3 = WARN + 0x01B,
+        REFERENCE_S4 = WARN + 0x01C,
+        REFERENCE_S5 = WARN + 0x01D,
+        REFERENCE_S6 = WARN + 0x01E,
+        NV_RATE = WARN + 0x020,
+        LOCKOUT = WARN + 0x021,
+        RETRY = WARN + 0x022,
+        NV_UNAVAILABLE = WARN + 0x023,
+        NOT_USED = WARN + 0x07F,
+    }
+}
+
 impl MarshalFixed for RC {
     const SIZE: usize = <u32 as MarshalFixed>::SIZE;
     type ARRAY = [u8; Self::SIZE];
@@ -199,6 +689,11 @@ pub enum ST {
     #[default]
     NoSessions = 0x8001,
     Sessions = 0x8002,
+    Creation = 0x8021,
+    Verified = 0x8022,
+    AuthSecret = 0x8023,
+    Hashcheck = 0x8024,
+    AuthSigned = 0x8025,
 }
 impl MarshalFixed for ST {
     const SIZE: usize = <u16 as MarshalFixed>::SIZE;
@@ -212,6 +707,11 @@ impl Unmarshal<'_> for ST {
         *self = match u16::unmarshal_val(buf)? {
             0x8001 => Self::NoSessions,
             0x8002 => Self::Sessions,
+            0x8021 => Self::Creation,
+            0x8022 => Self::Verified,
+            0x8023 => Self::AuthSecret,
+            0x8024 => Self::Hashcheck,
+            0x8025 => Self::AuthSigned,
             _ => return Err(UnmarshalError::InvalidValue),
         };
         Ok(())
@@ -260,6 +760,9 @@ pub enum Alg {
     Sha3_256 = 0x0027,
     Sha3_384 = 0x0028,
     Sha3_512 = 0x0029,
+    Shake128 = 0x002A,
+    Shake256 = 0x002B,
+    Cmac = 0x003F,
     Ctr = 0x0040,
     Ofb = 0x0041,
     Cbc = 0x0042,
@@ -318,6 +821,9 @@ impl Unmarshal<'_> for Alg {
             0x0027 => Self::Sha3_256,
             0x0028 => Self::Sha3_384,
             0x0029 => Self::Sha3_512,
+            0x002A => Self::Shake128,
+            0x002B => Self::Shake256,
+            0x003F => Self::Cmac,
             0x0040 => Self::Ctr,
             0x0041 => Self::Ofb,
             0x0042 => Self::Cbc,
@@ -343,7 +849,7 @@ impl Unmarshal<'_> for Option<Alg> {
 /// TPM_ECC_CURVE (TPMI_ECC_CURVE)
 ///
 /// List of reistered curve identifiers
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
 #[non_exhaustive]
 #[repr(u16)]
 pub enum EccCurve {
@@ -384,3 +890,197 @@ impl Unmarshal<'_> for EccCurve {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{commands::GetCapabilityResponse, polyfill::ToUsize, types::tpms, ResponseData};
+
+    #[test]
+    fn command_attributes() {
+        for (i, &cc) in CC::ALL.iter().enumerate() {
+            let attrs = cc.attributes().unwrap();
+            assert_eq!(attrs.command_code(), cc);
+            assert!(cc.auth_handles().unwrap() <= attrs.c_handles(), "{cc:?}");
+            if i > 0 {
+                assert!(CC::ALL[i - 1] < cc);
+            }
+        }
+        let attrs = CC::CreatePrimary.attributes().unwrap();
+        assert_eq!((attrs.c_handles(), attrs.r_handles()), (1, 1));
+        assert_eq!(attrs.bits(), 0x12000131);
+        assert_eq!(CC::NvUndefineSpaceSpecial.auth_handles(), Some(2));
+        let has = |cc: CC, flag| cc.attributes().unwrap().contains(flag);
+        assert!(has(CC::Clear, tpma::Cc::EXTENSIVE));
+        assert!(has(CC::SequenceComplete, tpma::Cc::FLUSHED));
+        assert!(!has(CC::NvRead, tpma::Cc::NV));
+    }
+
+    /// A GetCapability(Commands) response listing the commands of the
+    /// reference TPM2 simulator, with the TPMA_CC values transcribed from its
+    /// command attribute table (CommandAttributeData.h). The optional field
+    /// upgrade, FirmwareRead, and attached component commands are omitted.
+    #[rustfmt::skip]
+    const SIMULATOR_COMMANDS: &[u8] = &[
+        0x80, 0x01, 0x00, 0x00, 0x01, 0xD3, 0x00, 0x00, 0x00, 0x00, // header
+        0x00, // moreData
+        0x00, 0x00, 0x00, 0x02, // TPM_CAP_COMMANDS
+        0x00, 0x00, 0x00, 0x70, // count
+        0x04, 0x40, 0x01, 0x1F, // NvUndefineSpaceSpecial
+        0x04, 0x40, 0x01, 0x20, // EvictControl
+        0x02, 0xC0, 0x01, 0x21, // HierarchyControl
+        0x04, 0x40, 0x01, 0x22, // NvUndefineSpace
+        0x02, 0xC0, 0x01, 0x24, // ChangeEps
+        0x02, 0xC0, 0x01, 0x25, // ChangePps
+        0x02, 0xC0, 0x01, 0x26, // Clear
+        0x02, 0x40, 0x01, 0x27, // ClearControl
+        0x02, 0x40, 0x01, 0x28, // ClockSet
+        0x02, 0x40, 0x01, 0x29, // HierarchyChangeAuth
+        0x02, 0x40, 0x01, 0x2A, // NvDefineSpace
+        0x02, 0x40, 0x01, 0x2B, // PcrAllocate
+        0x02, 0x40, 0x01, 0x2C, // PcrSetAuthPolicy
+        0x02, 0x40, 0x01, 0x2D, // PpCommands
+        0x02, 0x40, 0x01, 0x2E, // SetPrimaryPolicy
+        0x02, 0x00, 0x01, 0x30, // ClockRateAdjust
+        0x12, 0x00, 0x01, 0x31, // CreatePrimary
+        0x02, 0x40, 0x01, 0x32, // NvGlobalWriteLock
+        0x04, 0x40, 0x01, 0x33, // GetCommandAuditDigest
+        0x04, 0x40, 0x01, 0x34, // NvIncrement
+        0x04, 0x40, 0x01, 0x35, // NvSetBits
+        0x04, 0x40, 0x01, 0x36, // NvExtend
+        0x04, 0x40, 0x01, 0x37, // NvWrite
+        0x04, 0x40, 0x01, 0x38, // NvWriteLock
+        0x02, 0x40, 0x01, 0x39, // DictionaryAttackLockReset
+        0x02, 0x40, 0x01, 0x3A, // DictionaryAttackParameters
+        0x02, 0x40, 0x01, 0x3B, // NvChangeAuth
+        0x02, 0x40, 0x01, 0x3C, // PcrEvent
+        0x02, 0x40, 0x01, 0x3D, // PcrReset
+        0x03, 0x00, 0x01, 0x3E, // SequenceComplete
+        0x02, 0x40, 0x01, 0x3F, // SetAlgorithmSet
+        0x02, 0x40, 0x01, 0x40, // SetCommandCodeAuditStatus
+        0x00, 0x40, 0x01, 0x42, // IncrementalSelfTest
+        0x00, 0x40, 0x01, 0x43, // SelfTest
+        0x00, 0x40, 0x01, 0x44, // Startup
+        0x00, 0x40, 0x01, 0x45, // Shutdown
+        0x00, 0x40, 0x01, 0x46, // StirRandom
+        0x04, 0x00, 0x01, 0x47, // ActivateCredential
+        0x04, 0x00, 0x01, 0x48, // Certify
+        0x06, 0x00, 0x01, 0x49, // PolicyNv
+        0x04, 0x00, 0x01, 0x4A, // CertifyCreation
+        0x04, 0x00, 0x01, 0x4B, // Duplicate
+        0x04, 0x00, 0x01, 0x4C, // GetTime
+        0x06, 0x00, 0x01, 0x4D, // GetSessionAuditDigest
+        0x04, 0x00, 0x01, 0x4E, // NvRead
+        0x04, 0x40, 0x01, 0x4F, // NvReadLock
+        0x04, 0x00, 0x01, 0x50, // ObjectChangeAuth
+        0x04, 0x00, 0x01, 0x51, // PolicySecret
+        0x04, 0x00, 0x01, 0x52, // Rewrap
+        0x02, 0x00, 0x01, 0x53, // Create
+        0x02, 0x00, 0x01, 0x54, // EcdhZGen
+        0x02, 0x00, 0x01, 0x55, // Mac
+        0x02, 0x00, 0x01, 0x56, // Import
+        0x12, 0x00, 0x01, 0x57, // Load
+        0x02, 0x00, 0x01, 0x58, // Quote
+        0x02, 0x00, 0x01, 0x59, // RsaDecrypt
+        0x12, 0x00, 0x01, 0x5B, // MacStart
+        0x02, 0x00, 0x01, 0x5C, // SequenceUpdate
+        0x02, 0x00, 0x01, 0x5D, // Sign
+        0x02, 0x00, 0x01, 0x5E, // Unseal
+        0x04, 0x00, 0x01, 0x60, // PolicySigned
+        0x10, 0x00, 0x01, 0x61, // ContextLoad
+        0x02, 0x00, 0x01, 0x62, // ContextSave
+        0x02, 0x00, 0x01, 0x63, // EcdhKeyGen
+        0x02, 0x00, 0x01, 0x64, // EncryptDecrypt
+        0x01, 0x00, 0x01, 0x65, // FlushContext
+        0x10, 0x00, 0x01, 0x67, // LoadExternal
+        0x02, 0x00, 0x01, 0x68, // MakeCredential
+        0x02, 0x00, 0x01, 0x69, // NvReadPublic
+        0x02, 0x00, 0x01, 0x6A, // PolicyAuthorize
+        0x02, 0x00, 0x01, 0x6B, // PolicyAuthValue
+        0x02, 0x00, 0x01, 0x6C, // PolicyCommandCode
+        0x02, 0x00, 0x01, 0x6D, // PolicyCounterTimer
+        0x02, 0x00, 0x01, 0x6E, // PolicyCpHash
+        0x02, 0x00, 0x01, 0x6F, // PolicyLocality
+        0x02, 0x00, 0x01, 0x70, // PolicyNameHash
+        0x02, 0x00, 0x01, 0x71, // PolicyOR
+        0x02, 0x00, 0x01, 0x72, // PolicyTicket
+        0x02, 0x00, 0x01, 0x73, // ReadPublic
+        0x02, 0x00, 0x01, 0x74, // RsaEncrypt
+        0x14, 0x00, 0x01, 0x76, // StartAuthSession
+        0x02, 0x00, 0x01, 0x77, // VerifySignature
+        0x00, 0x00, 0x01, 0x78, // EccParameters
+        0x00, 0x00, 0x01, 0x7A, // GetCapability
+        0x00, 0x00, 0x01, 0x7B, // GetRandom
+        0x00, 0x00, 0x01, 0x7C, // GetTestResult
+        0x00, 0x00, 0x01, 0x7D, // Hash
+        0x00, 0x00, 0x01, 0x7E, // PcrRead
+        0x02, 0x00, 0x01, 0x7F, // PolicyPcr
+        0x02, 0x00, 0x01, 0x80, // PolicyRestart
+        0x00, 0x00, 0x01, 0x81, // ReadClock
+        0x02, 0x40, 0x01, 0x82, // PcrExtend
+        0x02, 0x00, 0x01, 0x83, // PcrSetAuthValue
+        0x06, 0x00, 0x01, 0x84, // NvCertify
+        0x05, 0x40, 0x01, 0x85, // EventSequenceComplete
+        0x10, 0x00, 0x01, 0x86, // HashSequenceStart
+        0x02, 0x00, 0x01, 0x87, // PolicyPhysicalPresence
+        0x02, 0x00, 0x01, 0x88, // PolicyDuplicationSelect
+        0x02, 0x00, 0x01, 0x89, // PolicyGetDigest
+        0x00, 0x00, 0x01, 0x8A, // TestParms
+        0x02, 0x00, 0x01, 0x8B, // Commit
+        0x02, 0x00, 0x01, 0x8C, // PolicyPassword
+        0x02, 0x00, 0x01, 0x8D, // ZGen2Phase
+        0x00, 0x00, 0x01, 0x8E, // EcEphemeral
+        0x02, 0x00, 0x01, 0x8F, // PolicyNvWritten
+        0x02, 0x00, 0x01, 0x90, // PolicyTemplate
+        0x12, 0x00, 0x01, 0x91, // CreateLoaded
+        0x06, 0x00, 0x01, 0x92, // PolicyAuthorizeNv
+        0x02, 0x00, 0x01, 0x93, // EncryptDecrypt2
+        0x04, 0x00, 0x01, 0x97, // CertifyX509
+        0x02, 0x00, 0x01, 0x98, // ActSetTimeout
+        0x20, 0x00, 0x00, 0x00, // Vendor_TCG_Test
+    ];
+
+    #[test]
+    fn simulator_command_attributes() {
+        let size = u32::from_be_bytes(SIMULATOR_COMMANDS[2..6].try_into().unwrap());
+        assert_eq!(size.to_usize(), SIMULATOR_COMMANDS.len());
+        let mut rsp = GetCapabilityResponse::default();
+        let mut buf = &SIMULATOR_COMMANDS[10..];
+        rsp.unmarshal_params(&mut buf).unwrap();
+        assert!(buf.is_empty() && !rsp.more_data);
+        let tpms::CapabilityData::Commands(list) = rsp.capability_data else {
+            panic!("unexpected capability: {:?}", rsp.capability_data);
+        };
+        assert_eq!(list.len(), 0x70);
+        for attrs in list {
+            let cc = attrs.command_code();
+            if cc.is_vendor() {
+                assert_eq!(cc.attributes(), None);
+            } else {
+                assert_eq!(cc.attributes(), Some(attrs), "{cc:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn pcr_handles() {
+        assert_eq!(hc::PCR_LAST, 23);
+        assert_eq!(hc::pcr(23), Some(hc::PCR_LAST));
+        assert_eq!(hc::pcr(24), None);
+    }
+
+    #[test]
+    fn unknown_command_codes() {
+        let vendor = CC::unmarshal_val(&mut &[0x20, 0, 0, 0x01][..]).unwrap();
+        assert!(vendor.is_vendor());
+        assert_eq!(vendor.get(), 0x20000001);
+        assert_eq!(vendor.name(), None);
+        assert_eq!(vendor.attributes(), None);
+        assert_eq!(std::format!("{vendor:?}"), "CC(0x20000001)");
+
+        let cc = CC::unmarshal_val(&mut &[0, 0, 0x01, 0x7B][..]).unwrap();
+        assert_eq!(cc, CC::GetRandom);
+        assert!(!cc.is_vendor());
+        assert_eq!(std::format!("{cc:?}"), "GetRandom");
+    }
+}
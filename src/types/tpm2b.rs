@@ -52,6 +52,13 @@ impl Marshal for Option<Name> {
     }
 }
 
+impl Unmarshal<'_> for Name {
+    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
+        *self = Option::<Name>::unmarshal_val(buf)?.ok_or(UnmarshalError::InvalidValue)?;
+        Ok(())
+    }
+}
+
 impl Unmarshal<'_> for Option<Name> {
     fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
         let mut raw: &[u8] = Unmarshal::unmarshal_val(buf)?;
@@ -71,9 +78,13 @@ impl Unmarshal<'_> for Option<Name> {
     }
 }
 
-pub type PublicIn<'b> = dyn In<tpmt::Public<'b>>;
+pub type PublicIn<'b> = dyn In<tpmt::Public<'b>> + 'b;
 pub type PublicOut<'t> = Out<tpmt::Public<'t>>;
 pub type CreationData<'t> = Out<tpms::CreationData<'t>>;
+pub type EccPointIn<'b> = dyn In<tpms::EccPoint<'b>> + 'b;
+pub type EccPointOut<'t> = Out<tpms::EccPoint<'t>>;
+pub type NvPublicIn<'b> = dyn In<tpms::NvPublic<'b>> + 'b;
+pub type NvPublicOut<'t> = Out<tpms::NvPublic<'t>>;
 
 pub trait In<T>: Debug {
     fn marshal_impl(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError>;
@@ -95,12 +106,22 @@ impl<T> In<T> for [u8] {
     }
 }
 
-impl<T: Marshal> Marshal for dyn In<T> {
+impl<T: Marshal> Marshal for dyn In<T> + '_ {
     fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
         self.marshal_impl(buf)
     }
 }
 
+/// An absent buffer is marshalled as a zero-sized TPM2B.
+impl<T: Marshal> Marshal for Option<&dyn In<T>> {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        match self {
+            Some(b) => b.marshal_impl(buf),
+            None => 0u16.marshal(buf),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 #[repr(transparent)]
 pub struct Out<T: ?Sized>(pub T);
@@ -115,3 +136,20 @@ impl<'t, T: Unmarshal<'t> + ?Sized> Unmarshal<'t> for Out<T> {
         Ok(())
     }
 }
+
+/// A zero-sized TPM2B is unmarshalled as `None`.
+impl<'t, T: Unmarshal<'t> + Default> Unmarshal<'t> for Option<Out<T>> {
+    fn unmarshal(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        let mut raw: &'t [u8] = Unmarshal::unmarshal_val(buf)?;
+        *self = if raw.is_empty() {
+            None
+        } else {
+            let v = T::unmarshal_val(&mut raw)?;
+            if !raw.is_empty() {
+                return Err(UnmarshalError::BufferRemaining);
+            }
+            Some(Out(v))
+        };
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use core::mem;
 
 use bitflags::bitflags;
 
+use super::tpm;
 use crate::{MarshalFixed, UnmarshalFixed};
 
 /// TODO: Have an actual type for TPMA_LOCALITY
@@ -63,6 +64,148 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// TPMA_PERMANENT
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct Permanent: u32 {
+        const OWNER_AUTH_SET = 1 << 0;
+        const ENDORSEMENT_AUTH_SET = 1 << 1;
+        const LOCKOUT_AUTH_SET = 1 << 2;
+        const DISABLE_CLEAR = 1 << 8;
+        const IN_LOCKOUT = 1 << 9;
+        const TPM_GENERATED_EPS = 1 << 10;
+
+        const RESERVED = !(0b11100000111);
+    }
+}
+
+bitflags! {
+    /// TPMA_ALGORITHM
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct Algorithm: u32 {
+        const ASYMMETRIC = 1 << 0;
+        const SYMMETRIC = 1 << 1;
+        const HASH = 1 << 2;
+        const OBJECT = 1 << 3;
+        const SIGNING = 1 << 8;
+        const ENCRYPTING = 1 << 9;
+        const METHOD = 1 << 10;
+
+        const RESERVED = !(0b11100001111);
+    }
+}
+
+bitflags! {
+    /// TPMA_ACT
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct Act: u32 {
+        const SIGNALED = 1 << 0;
+        const PRESERVE_SIGNALED = 1 << 1;
+
+        const RESERVED = !(0b11);
+    }
+}
+
+bitflags! {
+    /// TPMA_NV
+    ///
+    /// The [`TPM_NT`](Self::TPM_NT) field holds the type of the NV Index, use
+    /// [`Nv::nt`] and [`Nv::with_nt`] to access it.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct Nv: u32 {
+        const PPWRITE = 1 << 0;
+        const OWNERWRITE = 1 << 1;
+        const AUTHWRITE = 1 << 2;
+        const POLICYWRITE = 1 << 3;
+        const TPM_NT = 0xF << 4;
+        const POLICY_DELETE = 1 << 10;
+        const WRITELOCKED = 1 << 11;
+        const WRITEALL = 1 << 12;
+        const WRITEDEFINE = 1 << 13;
+        const WRITE_STCLEAR = 1 << 14;
+        const GLOBALLOCK = 1 << 15;
+        const PPREAD = 1 << 16;
+        const OWNERREAD = 1 << 17;
+        const AUTHREAD = 1 << 18;
+        const POLICYREAD = 1 << 19;
+        const NO_DA = 1 << 25;
+        const ORDERLY = 1 << 26;
+        const CLEAR_STCLEAR = 1 << 27;
+        const READLOCKED = 1 << 28;
+        const WRITTEN = 1 << 29;
+        const PLATFORMCREATE = 1 << 30;
+        const READ_STCLEAR = 1 << 31;
+
+        const RESERVED = !(0b11111110000011111111110011111111);
+    }
+}
+
+impl Nv {
+    /// The type of the NV Index, or `None` if the type is unknown
+    pub const fn nt(&self) -> Option<tpm::NT> {
+        let nt = match (self.bits() & Self::TPM_NT.bits()) >> 4 {
+            0x0 => tpm::NT::Ordinary,
+            0x1 => tpm::NT::Counter,
+            0x2 => tpm::NT::Bits,
+            0x4 => tpm::NT::Extend,
+            0x8 => tpm::NT::PinFail,
+            0x9 => tpm::NT::PinPass,
+            _ => return None,
+        };
+        Some(nt)
+    }
+
+    /// Returns these attributes with the type of the NV Index set to `nt`
+    pub const fn with_nt(self, nt: tpm::NT) -> Self {
+        let bits = self.bits() & !Self::TPM_NT.bits();
+        Self::from_bits_truncate(bits | (nt as u32) << 4)
+    }
+}
+
+bitflags! {
+    /// TPMA_CC
+    ///
+    /// The [`COMMAND_INDEX`](Self::COMMAND_INDEX) and
+    /// [`C_HANDLES`](Self::C_HANDLES) fields are read with
+    /// [`Cc::command_code`] and [`Cc::c_handles`].
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct Cc: u32 {
+        const COMMAND_INDEX = 0xFFFF;
+        const NV = 1 << 22;
+        const EXTENSIVE = 1 << 23;
+        const FLUSHED = 1 << 24;
+        const C_HANDLES = 0x7 << 25;
+        const R_HANDLE = 1 << 28;
+        const V = 1 << 29;
+        const RES = 0x3 << 30;
+
+        const RESERVED = !(0b11111111110000001111111111111111);
+    }
+}
+
+impl Cc {
+    /// The command code (the command index, with [`V`](Self::V) set for
+    /// vendor-specific commands)
+    pub const fn command_code(&self) -> tpm::CC {
+        tpm::CC::new(self.bits() & (Self::COMMAND_INDEX.bits() | Self::V.bits()))
+    }
+
+    /// The number of handles in the handle area of the command
+    pub const fn c_handles(&self) -> usize {
+        ((self.bits() & Self::C_HANDLES.bits()) >> 25) as usize
+    }
+
+    /// The number of handles in the handle area of the response (0 or 1)
+    pub const fn r_handles(&self) -> usize {
+        self.contains(Self::R_HANDLE) as usize
+    }
+}
+
 macro_rules! impl_bitflags { ($($T: ty)+) => { $(
     impl MarshalFixed for $T {
         const SIZE: usize = mem::size_of::<Self>();
@@ -79,7 +222,7 @@ macro_rules! impl_bitflags { ($($T: ty)+) => { $(
     }
 )+ } }
 
-impl_bitflags!(Session Memory Object);
+impl_bitflags!(Session Memory Object Permanent Algorithm Act Nv Cc);
 
 #[cfg(test)]
 mod test {
@@ -89,5 +232,20 @@ mod test {
         assert_eq!(Session::all().bits(), u8::MAX);
         assert_eq!(Memory::all().bits(), u32::MAX);
         assert_eq!(Object::all().bits(), u32::MAX);
+        assert_eq!(Permanent::all().bits(), u32::MAX);
+        assert_eq!(Algorithm::all().bits(), u32::MAX);
+        assert_eq!(Act::all().bits(), u32::MAX);
+        assert_eq!(Nv::all().bits(), u32::MAX);
+        assert_eq!(Cc::all().bits(), u32::MAX);
+    }
+
+    #[test]
+    fn nv_type() {
+        let attrs = Nv::AUTHREAD | Nv::AUTHWRITE;
+        assert_eq!(attrs.nt(), Some(tpm::NT::Ordinary));
+        let attrs = attrs.with_nt(tpm::NT::PinPass);
+        assert_eq!(attrs.bits(), 0x00040094);
+        assert_eq!(attrs.nt(), Some(tpm::NT::PinPass));
+        assert_eq!(attrs.with_nt(tpm::NT::Counter).nt(), Some(tpm::NT::Counter));
     }
 }
@@ -19,6 +19,8 @@ pub type AlgSymMode = tpm::Alg;
 pub type AlgAsymScheme = tpm::Alg;
 /// TPMI_ALG_PUBLIC
 pub type AlgPublic = tpm::Alg;
+/// TPMI_ALG_SIG_SCHEME
+pub type AlgSigScheme = tpm::Alg;
 /// TPMI_ALG_KEYEDHASH_SCHEME
 pub type AlgKeyedHashScheme = tpm::Alg;
 
@@ -27,3 +29,13 @@ pub type RsaKeyBits = u16;
 
 /// TPMI_RH_HIERARCHY (can be NULL)
 pub type RhHierarchy = Handle;
+/// TPMI_RH_ENABLES (can be NULL)
+pub type RhEnables = Handle;
+/// TPMI_RH_PROVISION (OWNER or PLATFORM)
+pub type RhProvision = Handle;
+/// TPMI_RH_NV_INDEX
+pub type RhNvIndex = Handle;
+/// TPMI_DH_CONTEXT (transient object or session)
+pub type DhContext = Handle;
+/// TPMI_DH_SAVED (transient object or session, in a saved context)
+pub type DhSaved = Handle;
@@ -4,7 +4,7 @@
 
 use core::marker::PhantomData;
 
-use super::tpms;
+use super::{tpm, tpma, tpms, tpmt, Handle};
 use crate::{
     error::{MarshalError, UnmarshalError},
     polyfill::ToUsize,
@@ -15,6 +15,21 @@ pub type PcrSelectionIn<'b> = In<'b, tpms::PcrSelection>;
 pub type PcrSelectionOut<'t> = Out<'t, tpms::PcrSelection>;
 pub type DigestIn<'b> = In<'b, &'b [u8]>;
 pub type DigestOut<'t> = Out<'t, &'t [u8]>;
+pub type DigestValuesIn<'b> = In<'b, tpmt::Hash>;
+pub type DigestValuesOut<'t> = Out<'t, tpmt::Hash>;
+pub type AlgIn<'b> = In<'b, tpm::Alg>;
+pub type AlgOut<'t> = Out<'t, tpm::Alg>;
+pub type AlgPropertyOut<'t> = Out<'t, tpms::AlgProperty>;
+pub type HandleOut<'t> = Out<'t, Handle>;
+/// TPML_CC
+pub type CcOut<'t> = Out<'t, tpm::CC>;
+/// TPML_CCA
+pub type CcaOut<'t> = Out<'t, tpma::Cc>;
+pub type TaggedTpmPropertyOut<'t> = Out<'t, tpms::TaggedProperty>;
+pub type TaggedPcrPropertyOut<'t> = Out<'t, tpms::TaggedPcrSelect>;
+pub type EccCurveOut<'t> = Out<'t, tpm::EccCurve>;
+pub type TaggedPolicyOut<'t> = Out<'t, tpms::TaggedPolicy>;
+pub type ActDataOut<'t> = Out<'t, tpms::ActData>;
 
 /// Generic type for TPM inputs (just contains a slice)
 #[derive(Debug)]
@@ -113,9 +128,12 @@ impl<'t, T: Unmarshal<'t> + Default> Iterator for Out<'t, T> {
         if self.count == 0 {
             return None;
         }
-        self.count -= 1;
-        let v = T::unmarshal_val(&mut self.buf).unwrap();
-        Some(v)
+        // Each element was unmarshalled in exactly this way when the list was
+        // unmarshalled, so this cannot fail.
+        let v = T::unmarshal_val(&mut self.buf).ok();
+        debug_assert!(v.is_some(), "list element failed to unmarshal");
+        self.count = if v.is_some() { self.count - 1 } else { 0 };
+        v
     }
 }
 
@@ -130,10 +148,9 @@ impl<'t, T: Unmarshal<'t> + Default> Unmarshal<'t> for Out<'t, T> {
         self.count = u32::unmarshal_val(buf)?;
         let orig: &'t [u8] = buf;
 
-        // Validate but don't store the values
-        let mut v = T::default();
+        // Validate (the same way the iterator will) but don't store the values
         for _ in 0..self.count {
-            v.unmarshal(buf)?;
+            T::unmarshal_val(buf)?;
         }
         // Store the buffer we just read
         let data_len = orig.len() - buf.len();
@@ -141,3 +158,22 @@ impl<'t, T: Unmarshal<'t> + Default> Unmarshal<'t> for Out<'t, T> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate_lists() {
+        let mut buf: &[u8] = &[0, 0, 0, 2, 0x00, 0x04, 0x00, 0x0B];
+        let algs = AlgOut::unmarshal_val(&mut buf).unwrap();
+        assert!(algs.eq([tpm::Alg::Sha1, tpm::Alg::Sha256]));
+
+        // A list shorter than its count is rejected, rather than iterating
+        // over fewer elements.
+        let mut buf: &[u8] = &[0, 0, 0, 3, 0x00, 0x04, 0x00, 0x0B];
+        assert!(AlgOut::unmarshal_val(&mut buf).is_err());
+        let mut buf: &[u8] = &[0, 0, 0, 2, 0x00, 0x04, 0x00];
+        assert!(AlgOut::unmarshal_val(&mut buf).is_err());
+    }
+}
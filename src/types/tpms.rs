@@ -1,18 +1,27 @@
 //! `TPMS_*` Structure Types
 
+use core::{fmt, ops::Range, time::Duration};
+
 use super::{tpm, tpm2b, tpma, tpmi, tpml, tpmt, Handle};
 use crate::{
     error::{MarshalError, UnmarshalError},
-    marshal::{pop_array_mut, pop_slice},
+    marshal::{pop_slice, pop_slice_mut},
     polyfill::ToArr,
-    Marshal, MarshalFixed, Unmarshal,
+    Marshal, MarshalFixed, Unmarshal, UnmarshalFixed,
 };
 
 /// TPMS_TIME_INFO
 #[derive(Clone, Copy, Debug, Default)]
 pub struct TimeInfo {
-    time: u64,
-    clock_info: ClockInfo,
+    /// Milliseconds since the last TPM Reset or TPM Restart
+    pub time: u64,
+    pub clock_info: ClockInfo,
+}
+impl TimeInfo {
+    /// The `time` field as a [`Duration`]
+    pub const fn time_duration(&self) -> Duration {
+        Duration::from_millis(self.time)
+    }
 }
 impl MarshalFixed for TimeInfo {
     const SIZE: usize = <u64 as MarshalFixed>::SIZE + <ClockInfo as MarshalFixed>::SIZE;
@@ -23,19 +32,26 @@ impl MarshalFixed for TimeInfo {
     }
 }
 impl Unmarshal<'_> for TimeInfo {
-    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
-        self.time.unmarshal(buf)?;
-        self.clock_info.unmarshal(buf)
-    }
+    unmarshal_fields!('_, tpms::TimeInfo { time, clock_info });
 }
 
 /// TPMS_CLOCK_INFO
 #[derive(Clone, Copy, Debug, Default)]
 pub struct ClockInfo {
-    clock: u64,
-    reset_count: u32,
-    restart_count: u32,
-    safe: bool,
+    /// Milliseconds the TPM has been powered since the last TPM2_Clear. It
+    /// can also be advanced with [`ClockSet`](crate::commands::ClockSet).
+    pub clock: u64,
+    pub reset_count: u32,
+    pub restart_count: u32,
+    /// False if `clock` may have been reported before without being saved
+    /// to NV (e.g. after a power loss), so it could repeat
+    pub safe: bool,
+}
+impl ClockInfo {
+    /// The `clock` field as a [`Duration`]
+    pub const fn clock_duration(&self) -> Duration {
+        Duration::from_millis(self.clock)
+    }
 }
 
 impl MarshalFixed for ClockInfo {
@@ -53,12 +69,7 @@ impl MarshalFixed for ClockInfo {
     }
 }
 impl Unmarshal<'_> for ClockInfo {
-    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
-        self.clock.unmarshal(buf)?;
-        self.reset_count.unmarshal(buf)?;
-        self.restart_count.unmarshal(buf)?;
-        self.safe.unmarshal(buf)
-    }
+    unmarshal_fields!('_, tpms::ClockInfo { clock, reset_count, restart_count, safe });
 }
 
 /// TPMS_AUTH_COMMAND
@@ -71,13 +82,12 @@ pub struct AuthCommand<'a> {
 }
 
 impl Marshal for AuthCommand<'_> {
-    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.session_handle.marshal(buf)?;
-        self.nonce.marshal(buf)?;
-        self.session_attributes.marshal(buf)?;
-        self.hmac.marshal(buf)?;
-        Ok(())
-    }
+    marshal_fields!(tpms::AuthCommand {
+        session_handle,
+        nonce,
+        session_attributes,
+        hmac
+    });
 }
 
 /// TPMS_AUTH_RESPONSE
@@ -89,59 +99,161 @@ pub struct AuthResponse<'a> {
 }
 
 impl<'a> Unmarshal<'a> for AuthResponse<'a> {
-    fn unmarshal(&mut self, buf: &mut &'a [u8]) -> Result<(), UnmarshalError> {
-        self.nonce.unmarshal(buf)?;
-        self.session_attributes.unmarshal(buf)?;
-        self.hmac.unmarshal(buf)?;
-        Ok(())
-    }
+    unmarshal_fields!('a, tpms::AuthResponse { nonce, session_attributes, hmac });
 }
 
-const SIZE_OF_SELECT: usize = 3;
-pub const NUM_PCRS: usize = 8 * SIZE_OF_SELECT;
+/// Minimum `sizeofSelect` accepted by a TPM (PCR_SELECT_MIN)
+pub const MIN_SIZE_OF_SELECT: usize = 3;
+/// Maximum `sizeofSelect` that can be encoded in a [`PcrSelect`]
+pub const MAX_SIZE_OF_SELECT: usize = u8::MAX as usize;
+/// Number of PCRs every TPM must implement
+pub const MIN_PCRS: usize = 8 * MIN_SIZE_OF_SELECT;
+/// Number of PCRs that can be represented in a [`PcrSelect`]
+pub const MAX_PCRS: usize = 8 * MAX_SIZE_OF_SELECT;
 
 /// TPMS_PCR_SELECT
 ///
-/// TODO: Do we want to support anything other than 24 PCRs?
-pub type PcrSelect = [bool; NUM_PCRS];
+/// A set of PCR indices, stored as a bitmap. When marshalled, the smallest
+/// `sizeofSelect` (but at least [`MIN_SIZE_OF_SELECT`]) that contains all
+/// selected PCRs is used.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PcrSelect {
+    bits: [u8; MAX_SIZE_OF_SELECT],
+}
+
+impl PcrSelect {
+    /// Returns an empty selection.
+    pub const fn new() -> Self {
+        Self {
+            bits: [0; MAX_SIZE_OF_SELECT],
+        }
+    }
+
+    /// Returns a selection of all PCRs in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range.end` is greater than [`MAX_PCRS`].
+    pub fn range(range: Range<usize>) -> Self {
+        assert!(range.end <= MAX_PCRS, "PCR range too large");
+        range.collect()
+    }
+
+    /// Returns true if PCR `pcr` is selected.
+    pub fn contains(&self, pcr: usize) -> bool {
+        match self.bits.get(pcr / 8) {
+            Some(byte) => byte & (1 << (pcr % 8)) != 0,
+            None => false,
+        }
+    }
+
+    /// Selects PCR `pcr`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pcr` is not less than [`MAX_PCRS`].
+    pub fn insert(&mut self, pcr: usize) {
+        assert!(pcr < MAX_PCRS, "PCR index too large");
+        self.bits[pcr / 8] |= 1 << (pcr % 8);
+    }
+
+    /// Deselects PCR `pcr`.
+    pub fn remove(&mut self, pcr: usize) {
+        if let Some(byte) = self.bits.get_mut(pcr / 8) {
+            *byte &= !(1 << (pcr % 8));
+        }
+    }
+
+    /// Returns true if no PCRs are selected.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&byte| byte == 0)
+    }
+
+    /// Returns the number of selected PCRs.
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the PCRs selected in either `self` or `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a | b)
+    }
+
+    /// Returns the PCRs selected in both `self` and `other`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & b)
+    }
+
+    /// Returns the PCRs selected in `self` but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & !b)
+    }
+
+    /// Iterates over the selected PCR indices in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..MAX_PCRS).filter(|&pcr| self.contains(pcr))
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(u8, u8) -> u8) -> Self {
+        let mut out = Self::new();
+        for (o, (&a, &b)) in out.bits.iter_mut().zip(self.bits.iter().zip(&other.bits)) {
+            *o = f(a, b);
+        }
+        out
+    }
+
+    fn size_of_select(&self) -> usize {
+        let used = self
+            .bits
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .map_or(0, |i| i + 1);
+        used.max(MIN_SIZE_OF_SELECT)
+    }
+}
+
+impl Default for PcrSelect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for PcrSelect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<usize> for PcrSelect {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut sel = Self::new();
+        for pcr in iter {
+            sel.insert(pcr);
+        }
+        sel
+    }
+}
 
 impl Marshal for PcrSelect {
     fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        (SIZE_OF_SELECT as u8).marshal(buf)?;
-        let sel = pop_array_mut(buf)?;
-
-        *sel = [0; SIZE_OF_SELECT];
-        for (i, &bit) in self.iter().enumerate() {
-            if !bit {
-                continue;
-            }
-            let byte_idx = i / 8;
-            let bit_idx = i % 8;
-            sel[byte_idx] |= 1 << bit_idx;
-        }
+        let size = self.size_of_select();
+        (size as u8).marshal(buf)?;
+        pop_slice_mut(size, buf)?.copy_from_slice(&self.bits[..size]);
         Ok(())
     }
 }
 
 impl Unmarshal<'_> for PcrSelect {
     fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
+        // sizeofSelect is a u8, so it always fits in MAX_SIZE_OF_SELECT.
         let size: usize = u8::unmarshal_val(buf)?.into();
         let sel = pop_slice(size, buf)?;
 
-        *self = [false; NUM_PCRS];
-        for (byte_idx, &byte) in sel.iter().enumerate() {
-            for bit_idx in 0..8 {
-                let pcr_num = 8 * byte_idx + bit_idx;
-                if byte & (1 << bit_idx) == 0 {
-                    continue;
-                }
-                if pcr_num > NUM_PCRS {
-                    return Err(UnmarshalError::PcrTooLarge(pcr_num));
-                }
-                self[pcr_num] = true;
-            }
-        }
-
+        *self = Self::new();
+        self.bits[..size].copy_from_slice(sel);
         Ok(())
     }
 }
@@ -154,17 +266,11 @@ pub struct PcrSelection {
 }
 
 impl Marshal for PcrSelection {
-    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.hash.marshal(buf)?;
-        self.select.marshal(buf)
-    }
+    marshal_fields!(tpms::PcrSelection { hash, select });
 }
 
 impl Unmarshal<'_> for PcrSelection {
-    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
-        self.hash.unmarshal(buf)?;
-        self.select.unmarshal(buf)
-    }
+    unmarshal_fields!('_, tpms::PcrSelection { hash, select });
 }
 
 /// TPMS_SCHEME_HASH (not used)
@@ -184,17 +290,11 @@ pub struct SchemeXor {
 }
 
 impl Marshal for SchemeXor {
-    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
-        self.hash.marshal(buf)?;
-        self.kdf.marshal(buf)
-    }
+    marshal_fields!(tpms::SchemeXor { hash, kdf });
 }
 
 impl Unmarshal<'_> for SchemeXor {
-    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
-        self.hash.unmarshal(buf)?;
-        self.kdf.unmarshal(buf)
-    }
+    unmarshal_fields!('_, tpms::SchemeXor { hash, kdf });
 }
 
 /// TPMS_ASYM_PARMS
@@ -213,13 +313,17 @@ pub struct RsaParms {
     pub exponent: u32,
 }
 
+impl Marshal for RsaParms {
+    marshal_fields!(tpms::RsaParms {
+        symmetric,
+        scheme,
+        key_bits,
+        exponent
+    });
+}
+
 impl Unmarshal<'_> for RsaParms {
-    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
-        self.symmetric.unmarshal(buf)?;
-        self.scheme.unmarshal(buf)?;
-        self.key_bits.unmarshal(buf)?;
-        self.exponent.unmarshal(buf)
-    }
+    unmarshal_fields!('_, tpms::RsaParms { symmetric, scheme, key_bits, exponent });
 }
 
 /// TPMS_ECC_PARMS
@@ -231,13 +335,17 @@ pub struct EccParms {
     pub kdf: Option<tpmt::KdfScheme>,
 }
 
+impl Marshal for EccParms {
+    marshal_fields!(tpms::EccParms {
+        symmetric,
+        scheme,
+        curve_id,
+        kdf
+    });
+}
+
 impl Unmarshal<'_> for EccParms {
-    fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
-        self.symmetric.unmarshal(buf)?;
-        self.scheme.unmarshal(buf)?;
-        self.curve_id.unmarshal(buf)?;
-        self.kdf.unmarshal(buf)
-    }
+    unmarshal_fields!('_, tpms::EccParms { symmetric, scheme, curve_id, kdf });
 }
 
 /// TPMS_ECC_POINT
@@ -247,11 +355,35 @@ pub struct EccPoint<'t> {
     pub y: &'t [u8],
 }
 
+impl Marshal for EccPoint<'_> {
+    marshal_fields!(tpms::EccPoint { x, y });
+}
+
 impl<'t> Unmarshal<'t> for EccPoint<'t> {
-    fn unmarshal(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
-        self.x.unmarshal(buf)?;
-        self.y.unmarshal(buf)
-    }
+    unmarshal_fields!('t, tpms::EccPoint { x, y });
+}
+
+/// TPMS_SIGNATURE_RSA
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SignatureRsa<'t> {
+    pub hash: tpmi::AlgHash,
+    pub sig: &'t [u8],
+}
+
+impl<'t> Unmarshal<'t> for SignatureRsa<'t> {
+    unmarshal_fields!('t, tpms::SignatureRsa { hash, sig });
+}
+
+/// TPMS_SIGNATURE_ECC (also TPMS_SIGNATURE_{ECDSA,ECDAA,SM2,ECSCHNORR})
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SignatureEcc<'t> {
+    pub hash: tpmi::AlgHash,
+    pub signature_r: &'t [u8],
+    pub signature_s: &'t [u8],
+}
+
+impl<'t> Unmarshal<'t> for SignatureEcc<'t> {
+    unmarshal_fields!('t, tpms::SignatureEcc { hash, signature_r, signature_s });
 }
 
 /// TPMS_CREATION_DATA
@@ -265,3 +397,269 @@ pub struct CreationData<'t> {
     pub parent_qualified_name: tpm2b::Name,
     pub outside_info: &'t [u8],
 }
+
+/// TPMS_NV_PUBLIC
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NvPublic<'t> {
+    pub nv_index: tpmi::RhNvIndex,
+    pub name_alg: tpmi::AlgHash,
+    pub attributes: tpma::Nv,
+    pub auth_policy: &'t [u8],
+    pub data_size: u16,
+}
+
+impl NvPublic<'_> {
+    /// Computes the Name of the NV Index described by this structure.
+    ///
+    /// The Name is the `name_alg` digest of the marshalled structure. As this
+    /// crate does not implement any hash algorithms, `hash` is called with
+    /// `name_alg` and the data to be hashed. It must return a digest using
+    /// that algorithm.
+    pub fn name(
+        &self,
+        hash: impl FnOnce(tpmi::AlgHash, &[u8]) -> tpmt::Hash,
+    ) -> Result<tpm2b::Name, MarshalError> {
+        // Large enough for any auth_policy digest
+        let mut buf = [0u8; 128];
+        let mut out = &mut buf[..];
+        self.marshal(&mut out)?;
+        let len = out.len();
+        let len = buf.len() - len;
+        Ok(tpm2b::Name::Digest(hash(self.name_alg, &buf[..len])))
+    }
+}
+
+impl Marshal for NvPublic<'_> {
+    marshal_fields!(tpms::NvPublic {
+        nv_index,
+        name_alg,
+        attributes,
+        auth_policy,
+        data_size
+    });
+}
+
+impl<'t> Unmarshal<'t> for NvPublic<'t> {
+    unmarshal_fields!('t, tpms::NvPublic { nv_index, name_alg, attributes, auth_policy, data_size });
+}
+
+/// TPMS_CONTEXT
+///
+/// A saved object or session, returned by
+/// [`ContextSave`](crate::commands::ContextSave). The `context_blob` is
+/// encrypted and integrity protected by the TPM.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Context<'t> {
+    pub sequence: u64,
+    pub saved_handle: tpmi::DhSaved,
+    pub hierarchy: tpmi::RhHierarchy,
+    pub context_blob: &'t [u8],
+}
+
+impl Marshal for Context<'_> {
+    marshal_fields!(tpms::Context {
+        sequence,
+        saved_handle,
+        hierarchy,
+        context_blob
+    });
+}
+
+impl<'t> Unmarshal<'t> for Context<'t> {
+    unmarshal_fields!('t, tpms::Context { sequence, saved_handle, hierarchy, context_blob });
+}
+
+/// TPMS_ALG_PROPERTY
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlgProperty {
+    pub alg: tpm::Alg,
+    pub alg_properties: tpma::Algorithm,
+}
+
+impl Unmarshal<'_> for AlgProperty {
+    unmarshal_fields!('_, tpms::AlgProperty { alg, alg_properties });
+}
+
+/// TPMS_TAGGED_PROPERTY
+///
+/// The `property` is one of the [`tpm::pt`] constants.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaggedProperty {
+    pub property: u32,
+    pub value: u32,
+}
+
+impl MarshalFixed for TaggedProperty {
+    const SIZE: usize = 2 * <u32 as MarshalFixed>::SIZE;
+    type ARRAY = [u8; Self::SIZE];
+    fn marshal_fixed(&self, arr: &mut Self::ARRAY) {
+        self.property.marshal_fixed(arr[0..4].to_arr());
+        self.value.marshal_fixed(arr[4..8].to_arr());
+    }
+}
+impl UnmarshalFixed for TaggedProperty {
+    fn unmarshal_fixed(arr: &Self::ARRAY) -> Self {
+        Self {
+            property: u32::unmarshal_fixed(arr[0..4].try_into().unwrap()),
+            value: u32::unmarshal_fixed(arr[4..8].try_into().unwrap()),
+        }
+    }
+}
+
+/// TPMS_TAGGED_PCR_SELECT
+///
+/// The `tag` is one of the [`tpm::pt_pcr`] constants.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaggedPcrSelect {
+    pub tag: u32,
+    pub pcr_select: PcrSelect,
+}
+
+impl Unmarshal<'_> for TaggedPcrSelect {
+    unmarshal_fields!('_, tpms::TaggedPcrSelect { tag, pcr_select });
+}
+
+/// TPMS_TAGGED_POLICY
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaggedPolicy {
+    pub handle: Handle,
+    pub policy_hash: tpmt::Hash,
+}
+
+impl Unmarshal<'_> for TaggedPolicy {
+    unmarshal_fields!('_, tpms::TaggedPolicy { handle, policy_hash });
+}
+
+/// TPMS_ACT_DATA
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ActData {
+    pub handle: Handle,
+    pub timeout: u32,
+    pub attributes: tpma::Act,
+}
+
+impl Unmarshal<'_> for ActData {
+    unmarshal_fields!('_, tpms::ActData { handle, timeout, attributes });
+}
+
+/// TPMS_CAPABILITY_DATA (TPMU_CAPABILITIES)
+#[derive(Clone, Copy, Debug)]
+pub enum CapabilityData<'t> {
+    Algs(tpml::AlgPropertyOut<'t>),
+    Handles(tpml::HandleOut<'t>),
+    Commands(tpml::CcaOut<'t>),
+    PpCommands(tpml::CcOut<'t>),
+    AuditCommands(tpml::CcOut<'t>),
+    Pcrs(tpml::PcrSelectionOut<'t>),
+    TpmProperties(tpml::TaggedTpmPropertyOut<'t>),
+    PcrProperties(tpml::TaggedPcrPropertyOut<'t>),
+    EccCurves(tpml::EccCurveOut<'t>),
+    AuthPolicies(tpml::TaggedPolicyOut<'t>),
+    Act(tpml::ActDataOut<'t>),
+    /// The format of vendor properties is not specified, so we just return
+    /// the raw bytes.
+    VendorProperty(&'t [u8]),
+}
+
+impl CapabilityData<'_> {
+    pub const fn capability(&self) -> tpm::Cap {
+        match self {
+            Self::Algs(_) => tpm::Cap::Algs,
+            Self::Handles(_) => tpm::Cap::Handles,
+            Self::Commands(_) => tpm::Cap::Commands,
+            Self::PpCommands(_) => tpm::Cap::PpCommands,
+            Self::AuditCommands(_) => tpm::Cap::AuditCommands,
+            Self::Pcrs(_) => tpm::Cap::Pcrs,
+            Self::TpmProperties(_) => tpm::Cap::TpmProperties,
+            Self::PcrProperties(_) => tpm::Cap::PcrProperties,
+            Self::EccCurves(_) => tpm::Cap::EccCurves,
+            Self::AuthPolicies(_) => tpm::Cap::AuthPolicies,
+            Self::Act(_) => tpm::Cap::Act,
+            Self::VendorProperty(_) => tpm::Cap::VendorProperty,
+        }
+    }
+}
+
+impl Default for CapabilityData<'_> {
+    fn default() -> Self {
+        Self::Algs(Default::default())
+    }
+}
+
+impl<'t> Unmarshal<'t> for CapabilityData<'t> {
+    fn unmarshal(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        *self = match tpm::Cap::unmarshal_val(buf)? {
+            tpm::Cap::Algs => Self::Algs(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::Handles => Self::Handles(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::Commands => Self::Commands(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::PpCommands => Self::PpCommands(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::AuditCommands => Self::AuditCommands(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::Pcrs => Self::Pcrs(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::TpmProperties => Self::TpmProperties(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::PcrProperties => Self::PcrProperties(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::EccCurves => Self::EccCurves(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::AuthPolicies => Self::AuthPolicies(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::Act => Self::Act(Unmarshal::unmarshal_val(buf)?),
+            tpm::Cap::VendorProperty => Self::VendorProperty(core::mem::take(buf)),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn pcr_select() {
+        let a = PcrSelect::range(0..24);
+        let b: PcrSelect = [16, 23, 31].into_iter().collect();
+        assert_eq!(a.union(&b).len(), 25);
+        assert_eq!(a.intersection(&b).iter().collect::<Vec<_>>(), [16, 23]);
+        assert_eq!(b.difference(&a).iter().collect::<Vec<_>>(), [31]);
+
+        let mut buf = [0u8; 8];
+        let mut out = &mut buf[..];
+        b.marshal(&mut out).unwrap();
+        assert_eq!(out.len(), 3);
+        assert_eq!(buf[..5], [4, 0x00, 0x00, 0x81, 0x80]);
+
+        // Any sizeofSelect must be accepted without panicking
+        for size in [0, 3, 4, MAX_SIZE_OF_SELECT] {
+            let mut buf = [0xFF; 1 + MAX_SIZE_OF_SELECT];
+            buf[0] = size as u8;
+            let sel = PcrSelect::unmarshal_val(&mut &buf[..]).unwrap();
+            assert_eq!(sel.len(), 8 * size);
+        }
+    }
+    #[test]
+    fn nv_name() {
+        let public = NvPublic {
+            nv_index: 0x01500000,
+            name_alg: tpm::Alg::Sha256,
+            attributes: tpma::Nv::AUTHREAD | tpma::Nv::AUTHWRITE,
+            auth_policy: &[],
+            data_size: 32,
+        };
+        let name = public
+            .name(|alg, data| {
+                assert_eq!(alg, tpm::Alg::Sha256);
+                #[rustfmt::skip]
+                assert_eq!(data, [
+                    0x01, 0x50, 0x00, 0x00,
+                    0x00, 0x0B,
+                    0x00, 0x04, 0x00, 0x04,
+                    0x00, 0x00,
+                    0x00, 0x20,
+                ]);
+                tpmt::Hash::Sha256([7; 32])
+            })
+            .unwrap();
+        assert!(matches!(
+            name,
+            tpm2b::Name::Digest(tpmt::Hash::Sha256([7, ..]))
+        ));
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
 };
 
 /// TPMT_HA (TPMU_HA)
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[non_exhaustive]
 pub enum Hash {
     Sha1([u8; 20]),
@@ -48,7 +48,10 @@ impl Hash {
             Self::Sha3_512(d) => d,
         }
     }
-    fn unmarshal_with_alg(alg: tpmi::AlgHash, buf: &mut &[u8]) -> Result<Self, UnmarshalError> {
+    pub(crate) fn unmarshal_with_alg(
+        alg: tpmi::AlgHash,
+        buf: &mut &[u8],
+    ) -> Result<Self, UnmarshalError> {
         let v = match alg {
             tpm::Alg::Sha1 => Self::Sha1(*pop_array(buf)?),
             tpm::Alg::Sha256 => Self::Sha256(*pop_array(buf)?),
@@ -141,6 +144,15 @@ impl PublicParms {
             }),
         }
     }
+    /// Marshals the parameters without the algorithm (TPMU_PUBLIC_PARMS)
+    pub(crate) fn marshal_parms(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        match self {
+            PublicParms::KeyedHash(p) => p.marshal(buf),
+            PublicParms::SymCipher(p) => p.marshal(buf),
+            PublicParms::Rsa(p) => p.marshal(buf),
+            PublicParms::Ecc(p) => p.marshal(buf),
+        }
+    }
     fn unmarshal_with_alg(alg: tpmi::AlgPublic, buf: &mut &[u8]) -> Result<Self, UnmarshalError> {
         let v = match alg {
             tpm::Alg::KeyedHash => Self::KeyedHash(Unmarshal::unmarshal_val(buf)?),
@@ -153,6 +165,13 @@ impl PublicParms {
     }
 }
 
+impl Marshal for PublicParms {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        self.alg().marshal(buf)?;
+        self.marshal_parms(buf)
+    }
+}
+
 impl Default for PublicParms {
     fn default() -> Self {
         Self::KeyedHash(None)
@@ -175,6 +194,21 @@ impl KeyedHashScheme {
     }
 }
 
+impl Marshal for Option<KeyedHashScheme> {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        match self {
+            None => tpm::Alg::Null.marshal(buf),
+            Some(s) => {
+                s.alg().marshal(buf)?;
+                match s {
+                    KeyedHashScheme::Hmac(h) => h.marshal(buf),
+                    KeyedHashScheme::Xor(x) => x.marshal(buf),
+                }
+            }
+        }
+    }
+}
+
 impl Unmarshal<'_> for Option<KeyedHashScheme> {
     fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
         *self = Self::unmarshal_val(buf)?;
@@ -198,6 +232,23 @@ pub struct SymDefObject {
     pub mode: tpmi::AlgSymMode,
 }
 
+impl Marshal for SymDefObject {
+    marshal_fields!(tpmt::SymDefObject {
+        algorithm,
+        key_bits,
+        mode
+    });
+}
+
+impl Marshal for Option<SymDefObject> {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        match self {
+            None => tpm::Alg::Null.marshal(buf),
+            Some(s) => s.marshal(buf),
+        }
+    }
+}
+
 impl Unmarshal<'_> for SymDefObject {
     fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
         *self = Self::unmarshal_val(buf)?;
@@ -301,6 +352,23 @@ impl AsymScheme {
     }
 }
 
+impl Marshal for Option<AsymScheme> {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        let Some(s) = self else {
+            return tpm::Alg::Null.marshal(buf);
+        };
+        s.alg().marshal(buf)?;
+        match s {
+            AsymScheme::RsaEs => Ok(()),
+            AsymScheme::Ecdaa(h, count) => {
+                h.marshal(buf)?;
+                count.marshal(buf)
+            }
+            _ => s.hash().marshal(buf),
+        }
+    }
+}
+
 impl Unmarshal<'_> for Option<AsymScheme> {
     fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
         *self = Self::unmarshal_val(buf)?;
@@ -330,6 +398,121 @@ impl Unmarshal<'_> for Option<AsymScheme> {
     }
 }
 
+/// TPMT_SIG_SCHEME (TPMU_SIG_SCHEME)
+///
+/// Also effectivly includes the TPMS_SCHEME_{HASH,HMAC,ECDAA} types. For ECDAA,
+/// the second field is the `counter` returned by [`Commit`] or
+/// [`EcEphemeral`].
+///
+/// [`Commit`]: crate::commands::Commit
+/// [`EcEphemeral`]: crate::commands::EcEphemeral
+#[derive(Clone, Copy, Debug)]
+pub enum SigScheme {
+    Hmac(tpmi::AlgHash),
+    RsaSsa(tpmi::AlgHash),
+    RsaPss(tpmi::AlgHash),
+    Ecdsa(tpmi::AlgHash),
+    Ecdaa(tpmi::AlgHash, u16),
+    Sm2(tpmi::AlgHash),
+    EcSchnorr(tpmi::AlgHash),
+}
+
+impl SigScheme {
+    /// The signing algorithm
+    pub const fn alg(&self) -> tpmi::AlgSigScheme {
+        match self {
+            Self::Hmac(_) => tpm::Alg::Hmac,
+            Self::RsaSsa(_) => tpm::Alg::RsaSsa,
+            Self::RsaPss(_) => tpm::Alg::RsaPss,
+            Self::Ecdsa(_) => tpm::Alg::Ecdsa,
+            Self::Ecdaa(_, _) => tpm::Alg::Ecdaa,
+            Self::Sm2(_) => tpm::Alg::Sm2,
+            Self::EcSchnorr(_) => tpm::Alg::EcSchnorr,
+        }
+    }
+
+    /// The hash algorithm used in this signing method
+    pub const fn hash(&self) -> tpmi::AlgHash {
+        match *self {
+            Self::Hmac(h) => h,
+            Self::RsaSsa(h) => h,
+            Self::RsaPss(h) => h,
+            Self::Ecdsa(h) => h,
+            Self::Ecdaa(h, _) => h,
+            Self::Sm2(h) => h,
+            Self::EcSchnorr(h) => h,
+        }
+    }
+}
+
+impl Marshal for SigScheme {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        self.alg().marshal(buf)?;
+        self.hash().marshal(buf)?;
+        if let Self::Ecdaa(_, count) = self {
+            count.marshal(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl Marshal for Option<SigScheme> {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        match self {
+            None => tpm::Alg::Null.marshal(buf),
+            Some(s) => s.marshal(buf),
+        }
+    }
+}
+
+/// TPMT_SIGNATURE (TPMU_SIGNATURE)
+#[derive(Clone, Copy, Debug)]
+pub enum Signature<'t> {
+    Hmac(Hash),
+    RsaSsa(tpms::SignatureRsa<'t>),
+    RsaPss(tpms::SignatureRsa<'t>),
+    Ecdsa(tpms::SignatureEcc<'t>),
+    Ecdaa(tpms::SignatureEcc<'t>),
+    Sm2(tpms::SignatureEcc<'t>),
+    EcSchnorr(tpms::SignatureEcc<'t>),
+}
+
+impl Signature<'_> {
+    /// The signing algorithm
+    pub const fn alg(&self) -> tpmi::AlgSigScheme {
+        match self {
+            Self::Hmac(_) => tpm::Alg::Hmac,
+            Self::RsaSsa(_) => tpm::Alg::RsaSsa,
+            Self::RsaPss(_) => tpm::Alg::RsaPss,
+            Self::Ecdsa(_) => tpm::Alg::Ecdsa,
+            Self::Ecdaa(_) => tpm::Alg::Ecdaa,
+            Self::Sm2(_) => tpm::Alg::Sm2,
+            Self::EcSchnorr(_) => tpm::Alg::EcSchnorr,
+        }
+    }
+}
+
+impl<'t> Unmarshal<'t> for Option<Signature<'t>> {
+    fn unmarshal(&mut self, buf: &mut &'t [u8]) -> Result<(), UnmarshalError> {
+        *self = Self::unmarshal_val(buf)?;
+        Ok(())
+    }
+
+    fn unmarshal_val(buf: &mut &'t [u8]) -> Result<Self, UnmarshalError> {
+        Ok(Some(match tpmi::AlgSigScheme::unmarshal_val(buf)? {
+            tpm::Alg::Null => return Ok(None),
+            tpm::Alg::Hmac => Signature::Hmac(Unmarshal::unmarshal_val(buf)?),
+            tpm::Alg::RsaSsa => Signature::RsaSsa(Unmarshal::unmarshal_val(buf)?),
+            tpm::Alg::RsaPss => Signature::RsaPss(Unmarshal::unmarshal_val(buf)?),
+            tpm::Alg::Ecdsa => Signature::Ecdsa(Unmarshal::unmarshal_val(buf)?),
+            tpm::Alg::Ecdaa => Signature::Ecdaa(Unmarshal::unmarshal_val(buf)?),
+            tpm::Alg::Sm2 => Signature::Sm2(Unmarshal::unmarshal_val(buf)?),
+            tpm::Alg::EcSchnorr => Signature::EcSchnorr(Unmarshal::unmarshal_val(buf)?),
+            _ => return Err(UnmarshalError::InvalidValue),
+        }))
+    }
+}
+
 /// TPMT_KDF_SCHEME (TPMU_KDF_SCHEME)
 #[derive(Clone, Copy, Debug, Default)]
 pub struct KdfScheme {
@@ -337,6 +520,18 @@ pub struct KdfScheme {
     pub hash: tpmi::AlgHash,
 }
 
+impl Marshal for Option<KdfScheme> {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        match self {
+            None => tpm::Alg::Null.marshal(buf),
+            Some(k) => {
+                k.scheme.marshal(buf)?;
+                k.hash.marshal(buf)
+            }
+        }
+    }
+}
+
 impl Unmarshal<'_> for Option<KdfScheme> {
     fn unmarshal(&mut self, buf: &mut &[u8]) -> Result<(), UnmarshalError> {
         *self = Self::unmarshal_val(buf)?;
@@ -391,3 +586,69 @@ pub struct TkCreation<'a> {
     pub hierarchy: tpmi::RhHierarchy,
     pub digest: &'a [u8],
 }
+
+/// TPMT_TK_HASHCHECK
+///
+/// The default value is the NULL Ticket, which is used when signing with an
+/// unrestricted key.
+#[derive(Clone, Copy, Debug)]
+pub struct TkHashcheck<'a> {
+    pub hierarchy: tpmi::RhHierarchy,
+    pub digest: &'a [u8],
+}
+
+impl Default for TkHashcheck<'_> {
+    fn default() -> Self {
+        Self {
+            hierarchy: tpm::rh::NULL,
+            digest: &[],
+        }
+    }
+}
+
+impl Marshal for TkHashcheck<'_> {
+    fn marshal(&self, buf: &mut &mut [u8]) -> Result<(), MarshalError> {
+        tpm::ST::Hashcheck.marshal(buf)?;
+        self.hierarchy.marshal(buf)?;
+        self.digest.marshal(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn public_parms_roundtrip() {
+        let parms = [
+            PublicParms::Rsa(tpms::RsaParms {
+                symmetric: Some(SymDefObject {
+                    algorithm: tpm::Alg::Aes,
+                    key_bits: 128,
+                    mode: tpm::Alg::Cfb,
+                }),
+                scheme: Some(AsymScheme::RsaSsa(tpm::Alg::Sha256)),
+                key_bits: 2048,
+                exponent: 0,
+            }),
+            PublicParms::Ecc(tpms::EccParms {
+                scheme: Some(AsymScheme::Ecdaa(tpm::Alg::Sha256, 3)),
+                curve_id: tpm::EccCurve::BnP256,
+                ..Default::default()
+            }),
+            PublicParms::KeyedHash(Some(KeyedHashScheme::Hmac(tpm::Alg::Sha384))),
+        ];
+        for p in parms {
+            let mut buf = [0u8; 64];
+            let mut out = &mut buf[..];
+            p.marshal(&mut out).unwrap();
+            let len = 64 - out.len();
+
+            let mut input = &buf[..len];
+            let alg = tpmi::AlgPublic::unmarshal_val(&mut input).unwrap();
+            let q = PublicParms::unmarshal_with_alg(alg, &mut input).unwrap();
+            assert!(input.is_empty());
+            assert_eq!(format!("{p:?}"), format!("{q:?}"));
+        }
+    }
+}
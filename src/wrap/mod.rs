@@ -0,0 +1,328 @@
+//! Wrappers adding behavior to any [`Tpm`]
+//!
+//! Each wrapper implements [`Tpm`] by forwarding to the inner TPM, so
+//! wrappers can be nested. [`ResourceManager`] swaps objects and sessions in
+//! and out of TPM memory, while [`SharedTpm`] allows a wrapped TPM to be used
+//! from many threads.
+
+use core::time::Duration;
+
+#[cfg(feature = "alloc")]
+mod rm;
+#[cfg(feature = "alloc")]
+#[doc(cfg(feature = "alloc"))]
+pub use rm::ResourceManager;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "std")]
+#[doc(cfg(feature = "std"))]
+pub use shared::{SharedTpm, SharedTpmGuard};
+
+use crate::{
+    commands::Startup,
+    error::{DriverError, TpmError},
+    types::tpm,
+    Error, Tpm, TpmRun,
+};
+
+/// Re-runs commands which fail with a retryable warning
+///
+/// The TPM returns warnings such as `TPM_RC_RETRY`, `TPM_RC_YIELDED`,
+/// `TPM_RC_TESTING` and `TPM_RC_NV_RATE` when a command could not be run
+/// right now, but might succeed later (see [`TpmError::is_retryable`]). This
+/// wrapper sleeps and then re-runs the command (re-marshalling it, so any
+/// sessions are updated). The delay starts at `initial_delay` and doubles
+/// after each failed attempt, up to `max_delay`.
+///
+/// Only attempts failing with a retryable warning count towards the maximum
+/// number of attempts, so re-runs by other wrappers (such as
+/// [`AutoStartup`]) don't use them up.
+#[derive(Debug)]
+pub struct Retry<T> {
+    tpm: T,
+    sleep: fn(Duration),
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    /// Number of times the current command has been retried by this wrapper
+    retries: u32,
+}
+
+impl<T: Tpm> Retry<T> {
+    /// Wraps `tpm`, sleeping with [`std::thread::sleep`].
+    ///
+    /// By default, a command is attempted at most 10 times, with delays
+    /// between 10 milliseconds and 1 second.
+    #[cfg(feature = "std")]
+    #[doc(cfg(feature = "std"))]
+    pub fn new(tpm: T) -> Self {
+        Self::with_sleep(tpm, std::thread::sleep)
+    }
+
+    /// Wraps `tpm`, sleeping by calling `sleep`.
+    pub fn with_sleep(tpm: T, sleep: fn(Duration)) -> Self {
+        Self {
+            tpm,
+            sleep,
+            max_attempts: 10,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(1),
+            retries: 0,
+        }
+    }
+
+    /// Sets the maximum number of times a command is run (including the
+    /// first attempt).
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry, and the maximum delay.
+    pub fn backoff(mut self, initial_delay: Duration, max_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn into_inner(self) -> T {
+        self.tpm
+    }
+
+    /// The delay after the command has failed `attempt` times
+    fn delay(&self, attempt: u32) -> Duration {
+        backoff(self.initial_delay, self.max_delay, attempt)
+    }
+}
+
+/// The delay after `attempt` failures, starting at `initial` and doubling
+/// each time, up to `max`
+pub(crate) fn backoff(initial: Duration, max: Duration, attempt: u32) -> Duration {
+    let factor = 1u32 << attempt.saturating_sub(1).min(31);
+    initial.saturating_mul(factor).min(max)
+}
+
+impl<T: Tpm> Tpm for Retry<T> {
+    #[inline]
+    fn command_buf(&mut self) -> &mut [u8] {
+        self.tpm.command_buf()
+    }
+    #[inline]
+    fn response_buf(&self) -> &[u8] {
+        self.tpm.response_buf()
+    }
+    #[inline]
+    fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
+        self.tpm.execute_command(cmd_size)
+    }
+
+    fn should_retry(&mut self, code: tpm::CC, err: TpmError, attempt: u32) -> Result<bool, Error> {
+        if attempt == 1 {
+            self.retries = 0;
+        }
+        if err.is_retryable() && self.retries + 1 < self.max_attempts {
+            self.retries += 1;
+            (self.sleep)(self.delay(self.retries));
+            return Ok(true);
+        }
+        self.tpm
+            .should_retry(code, err, attempt.saturating_sub(self.retries))
+    }
+}
+
+/// Runs TPM2_Startup when a command fails with `TPM_RC_INITIALIZE`
+///
+/// Simulators and TPMs used during early boot may not have been started yet,
+/// so the first command fails with `TPM_RC_INITIALIZE`. This wrapper then
+/// runs [`Startup`] with the configured type and re-runs the original
+/// command. As a `TPM_SU_CLEAR` startup resets the PCRs, callers can check
+/// [`performed_startup`](Self::performed_startup) to see if this happened.
+#[derive(Debug)]
+pub struct AutoStartup<T> {
+    tpm: T,
+    startup_type: tpm::SU,
+    performed_startup: bool,
+    /// If TPM2_Startup was run for the current command
+    retried: bool,
+}
+
+impl<T: Tpm> AutoStartup<T> {
+    pub fn new(tpm: T, startup_type: tpm::SU) -> Self {
+        Self {
+            tpm,
+            startup_type,
+            performed_startup: false,
+            retried: false,
+        }
+    }
+
+    /// Returns true if this wrapper has run TPM2_Startup.
+    pub fn performed_startup(&self) -> bool {
+        self.performed_startup
+    }
+
+    pub fn into_inner(self) -> T {
+        self.tpm
+    }
+}
+
+impl<T: Tpm> Tpm for AutoStartup<T> {
+    #[inline]
+    fn command_buf(&mut self) -> &mut [u8] {
+        self.tpm.command_buf()
+    }
+    #[inline]
+    fn response_buf(&self) -> &[u8] {
+        self.tpm.response_buf()
+    }
+    #[inline]
+    fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
+        self.tpm.execute_command(cmd_size)
+    }
+
+    fn should_retry(&mut self, code: tpm::CC, err: TpmError, attempt: u32) -> Result<bool, Error> {
+        if attempt == 1 {
+            self.retried = false;
+        }
+        if err.base() == tpm::rc::INITIALIZE && code != tpm::CC::Startup && !self.retried {
+            self.tpm.run(Startup {
+                startup_type: self.startup_type,
+            })?;
+            self.performed_startup = true;
+            self.retried = true;
+            return Ok(true);
+        }
+        self.tpm
+            .should_retry(code, err, attempt.saturating_sub(self.retried.into()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::{vec, vec::Vec};
+
+    use super::*;
+    use crate::{
+        commands::GetRandom,
+        test::{Respond, ScriptTpm},
+        types::{tpms, Auth, PasswordAuth},
+        Command, TpmRun,
+    };
+
+    /// Fake TPM returning each of `codes` in turn, then success (with an
+    /// empty GetRandom response)
+    pub(crate) fn sequence_tpm(codes: &[u32]) -> ScriptTpm<impl Respond + '_> {
+        let mut codes = codes.iter();
+        ScriptTpm::new(move |code, _: &[u8]| {
+            let rc = codes.next().copied().unwrap_or(0);
+            match code {
+                tpm::CC::GetRandom if rc == 0 => (rc, vec![0, 0]),
+                _ => (rc, Vec::new()),
+            }
+        })
+    }
+
+    /// An Auth counting how many times it was marshalled
+    #[derive(Debug, Default)]
+    struct CountingAuth(AtomicUsize);
+
+    impl Auth for CountingAuth {
+        fn get_auth(&self) -> tpms::AuthCommand<'_> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            PasswordAuth(&[]).get_auth()
+        }
+        fn set_auth(&self, _: &tpms::AuthResponse) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn code(tpm: &mut impl Tpm) -> Option<u32> {
+        let cmd = GetRandom { bytes_requested: 0 };
+        match tpm.run(cmd) {
+            Ok(_) => None,
+            Err(e) => Some(e.tpm_error().unwrap().get()),
+        }
+    }
+
+    #[test]
+    fn retry() {
+        let codes = [tpm::rc::RETRY, tpm::rc::TESTING, tpm::rc::YIELDED];
+        let mut tpm = Retry::with_sleep(sequence_tpm(&codes), |_| {});
+        assert_eq!(code(&mut tpm), None);
+        assert_eq!(tpm.tpm.commands.len(), 4);
+
+        let mut tpm = Retry::with_sleep(sequence_tpm(&codes), |_| {}).max_attempts(2);
+        assert_eq!(code(&mut tpm), Some(tpm::rc::TESTING));
+        assert_eq!(tpm.tpm.commands.len(), 2);
+
+        let codes = [tpm::rc::LOCKOUT];
+        let mut tpm = Retry::with_sleep(sequence_tpm(&codes), |_| {});
+        assert_eq!(code(&mut tpm), Some(tpm::rc::LOCKOUT));
+        assert_eq!(tpm.tpm.commands.len(), 1);
+    }
+
+    #[test]
+    fn retry_remarshals() {
+        let codes = [tpm::rc::RETRY, tpm::rc::RETRY];
+        let mut tpm = Retry::with_sleep(sequence_tpm(&codes), |_| {});
+        let auth = CountingAuth::default();
+        let cmd = GetRandom { bytes_requested: 0 }.with_auth(&auth);
+        tpm.run(cmd).unwrap();
+        assert_eq!(auth.0.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn retry_delay() {
+        let tpm = Retry::with_sleep(sequence_tpm(&[]), |_| {})
+            .backoff(Duration::from_millis(10), Duration::from_millis(50));
+        assert_eq!(tpm.delay(1), Duration::from_millis(10));
+        assert_eq!(tpm.delay(2), Duration::from_millis(20));
+        assert_eq!(tpm.delay(3), Duration::from_millis(40));
+        assert_eq!(tpm.delay(4), Duration::from_millis(50));
+        assert_eq!(tpm.delay(100), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn auto_startup() {
+        let codes = [tpm::rc::INITIALIZE];
+        let mut tpm = AutoStartup::new(sequence_tpm(&codes), tpm::SU::Clear);
+        assert_eq!(code(&mut tpm), None);
+        assert!(tpm.performed_startup());
+        let random = tpm::CC::GetRandom;
+        assert_eq!(tpm.tpm.commands, [random, tpm::CC::Startup, random]);
+
+        // Startup is only attempted once per command
+        let codes = [tpm::rc::INITIALIZE, 0, tpm::rc::INITIALIZE];
+        let mut tpm = AutoStartup::new(sequence_tpm(&codes), tpm::SU::State);
+        assert_eq!(code(&mut tpm), Some(tpm::rc::INITIALIZE));
+        assert_eq!(tpm.tpm.commands.len(), 3);
+
+        let codes = [tpm::rc::INITIALIZE, tpm::rc::FAILURE];
+        let mut tpm = AutoStartup::new(sequence_tpm(&codes), tpm::SU::Clear);
+        assert_eq!(code(&mut tpm), Some(tpm::rc::FAILURE));
+        assert!(!tpm.performed_startup());
+
+        let mut tpm = AutoStartup::new(sequence_tpm(&[]), tpm::SU::Clear);
+        assert_eq!(code(&mut tpm), None);
+        assert!(!tpm.performed_startup());
+    }
+
+    #[test]
+    fn stacked_retries() {
+        // AutoStartup still starts the TPM after Retry has retried
+        let codes = [tpm::rc::RETRY, tpm::rc::INITIALIZE];
+        let tpm = Retry::with_sleep(sequence_tpm(&codes), |_| {});
+        let mut tpm = AutoStartup::new(tpm, tpm::SU::Clear);
+        assert_eq!(code(&mut tpm), None);
+        assert!(tpm.performed_startup());
+        assert_eq!(tpm.tpm.tpm.commands.len(), 4);
+
+        // Running Startup doesn't use up an attempt
+        let codes = [tpm::rc::INITIALIZE, 0, tpm::rc::RETRY];
+        let tpm = AutoStartup::new(sequence_tpm(&codes), tpm::SU::Clear);
+        let mut tpm = Retry::with_sleep(tpm, |_| {}).max_attempts(2);
+        assert_eq!(code(&mut tpm), None);
+        assert_eq!(tpm.tpm.tpm.commands.len(), 4);
+    }
+}
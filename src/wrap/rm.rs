@@ -0,0 +1,751 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+use crate::{
+    commands::{ContextLoad, ContextSave, FlushContext, GetCapability},
+    error::{DriverError, ErrorKind, UnmarshalError},
+    marshal::{read_u16, read_u32},
+    polyfill::ToUsize,
+    types::{tpm, tpma, tpms, CommandHeader, Handle, ResponseHeader},
+    Error, MarshalFixed, Tpm, TpmRun,
+};
+
+/// Virtual object handles are allocated from this range, so they can't be
+/// confused with the TPM's own transient handles.
+const VIRTUAL_FIRST: Handle = 0x80FF0000;
+const VIRTUAL_LAST: Handle = 0x80FFFFFF;
+
+/// TPMA_SESSION continueSession
+const CONTINUE_SESSION: u8 = 0x01;
+/// Number of commands to read per TPM2_GetCapability call
+const MAX_CAP_CC: u32 = 256;
+
+/// A userspace resource manager, like the kernel's `/dev/tpmrm0`
+///
+/// A TPM can only hold a few transient objects and sessions at once. This
+/// wrapper keeps track of the objects and sessions created through it. When
+/// the TPM runs out of room (`TPM_RC_OBJECT_MEMORY` or
+/// `TPM_RC_SESSION_MEMORY`), the least recently used ones not needed by the
+/// current command are saved with [`ContextSave`], and are loaded again with
+/// [`ContextLoad`] when they are next used.
+///
+/// As a reloaded object gets a new handle, object handles returned by the TPM
+/// are replaced with virtual handles, which stay the same for the lifetime of
+/// the object. Session handles don't change when reloaded, so are returned
+/// as-is. Commands using transient objects or sessions not created through
+/// this wrapper fail with `TPM_RC_HANDLE`. Everything still loaded is flushed
+/// when the wrapper is dropped.
+///
+/// The handles in each command are found using the command attributes
+/// reported by the TPM, which are read when the first command (other than
+/// TPM2_Startup) is run. Note
+/// that handles listed by TPM2_GetCapability are not virtualized, and that
+/// `TPM_RC_CONTEXT_GAP` is returned to the caller.
+#[derive(Debug)]
+pub struct ResourceManager<T: Tpm> {
+    tpm: T,
+    cmd: Vec<u8>,
+    rsp: Vec<u8>,
+    /// TPMA_CC of each command supported by the TPM, by command code
+    commands: BTreeMap<tpm::CC, tpma::Cc>,
+    /// Managed objects (by virtual handle) and sessions (by handle)
+    entries: BTreeMap<Handle, Entry>,
+    next_handle: Handle,
+    /// Incremented for each command, to find the least recently used entry
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    state: State,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+enum State {
+    /// Loaded in the TPM, with the given handle
+    Loaded(Handle),
+    Saved(SavedContext),
+}
+
+/// An owned [`tpms::Context`]
+#[derive(Debug)]
+struct SavedContext {
+    sequence: u64,
+    saved_handle: Handle,
+    hierarchy: Handle,
+    blob: Vec<u8>,
+}
+
+impl SavedContext {
+    fn new(context: &tpms::Context) -> Self {
+        Self {
+            sequence: context.sequence,
+            saved_handle: context.saved_handle,
+            hierarchy: context.hierarchy,
+            blob: context.context_blob.to_vec(),
+        }
+    }
+
+    fn context(&self) -> tpms::Context<'_> {
+        tpms::Context {
+            sequence: self.sequence,
+            saved_handle: self.saved_handle,
+            hierarchy: self.hierarchy,
+            context_blob: &self.blob,
+        }
+    }
+}
+
+/// A managed handle in a command
+#[derive(Debug)]
+struct HandleRef {
+    offset: usize,
+    handle: Handle,
+    /// The N, P and S bits of a TPM_RC referring to this handle
+    location: u32,
+    /// If the handle is no longer valid after the command succeeds
+    flush: bool,
+}
+
+fn is_session(handle: Handle) -> bool {
+    matches!(
+        tpm::ht::of(handle),
+        tpm::ht::HMAC_SESSION | tpm::ht::POLICY_SESSION
+    )
+}
+
+fn is_managed(handle: Handle) -> bool {
+    tpm::ht::of(handle) == tpm::ht::TRANSIENT || is_session(handle)
+}
+
+/// Finds the managed handles in the handle and authorization areas of `cmd`
+/// (and the parameter of TPM2_FlushContext). Returns `None` if the command is
+/// malformed, in which case it is sent to the TPM unchanged.
+fn parse_handles(cmd: &[u8], code: tpm::CC, attrs: tpma::Cc) -> Option<Vec<HandleRef>> {
+    let mut refs = Vec::new();
+    let c_handles = attrs.c_handles();
+    for i in 0..c_handles {
+        let offset = CommandHeader::SIZE + 4 * i;
+        let handle = read_u32(cmd, offset)?;
+        refs.push(HandleRef {
+            offset,
+            handle,
+            location: (i as u32 + 1) << 8,
+            // A session saved by the caller is no longer ours to manage
+            flush: attrs.contains(tpma::Cc::FLUSHED)
+                || (code == tpm::CC::ContextSave && is_session(handle)),
+        });
+    }
+
+    let mut offset = CommandHeader::SIZE + 4 * c_handles;
+    if read_u16(cmd, 0)? == tpm::ST::Sessions as u16 {
+        let end = offset.checked_add(4 + read_u32(cmd, offset)?.to_usize())?;
+        offset += 4;
+        for i in 1.. {
+            if offset >= end {
+                break;
+            }
+            let handle = read_u32(cmd, offset)?;
+            let attrs_offset = offset + 6 + usize::from(read_u16(cmd, offset + 4)?);
+            let attributes = *cmd.get(attrs_offset)?;
+            refs.push(HandleRef {
+                offset,
+                handle,
+                location: tpm::rc::S | i << 8,
+                flush: attributes & CONTINUE_SESSION == 0,
+            });
+            offset = attrs_offset + 3 + usize::from(read_u16(cmd, attrs_offset + 1)?);
+        }
+    }
+
+    if code == tpm::CC::FlushContext {
+        refs.push(HandleRef {
+            offset,
+            handle: read_u32(cmd, offset)?,
+            location: tpm::rc::P | 1 << 8,
+            flush: true,
+        });
+    }
+    refs.retain(|r| is_managed(r.handle));
+    Some(refs)
+}
+
+impl<T: Tpm> ResourceManager<T> {
+    pub fn new(mut tpm: T) -> Self {
+        let cmd = vec![0; tpm.command_buf().len()];
+        Self {
+            tpm,
+            cmd,
+            rsp: Vec::new(),
+            commands: BTreeMap::new(),
+            entries: BTreeMap::new(),
+            next_handle: VIRTUAL_FIRST,
+            clock: 0,
+        }
+    }
+
+    /// Returns the TPMA_CC of the command `code`, first reading the
+    /// attributes of all commands from the TPM if needed.
+    ///
+    /// If the TPM can't report them (e.g. with `TPM_RC_INITIALIZE` before
+    /// TPM2_Startup), the attributes from the spec are used and the TPM is
+    /// asked again on the next command.
+    fn attributes(&mut self, code: tpm::CC) -> Result<Option<tpma::Cc>, Error> {
+        if self.commands.is_empty() && code != tpm::CC::Startup {
+            match self.read_attributes() {
+                Ok(()) => {}
+                Err(err) if err.tpm_error().is_some() => self.commands.clear(),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(self.commands.get(&code).copied().or(code.attributes()))
+    }
+
+    fn read_attributes(&mut self) -> Result<(), Error> {
+        let mut property = 0;
+        loop {
+            let rsp = self.tpm.run(GetCapability {
+                capability: tpm::Cap::Commands,
+                property,
+                property_count: MAX_CAP_CC,
+            })?;
+            let tpms::CapabilityData::Commands(list) = rsp.capability_data else {
+                return Err(UnmarshalError::InvalidValue.into());
+            };
+            let mut last = None;
+            for attrs in list {
+                let code = attrs.command_code();
+                self.commands.insert(code, attrs);
+                last = Some(code);
+            }
+            match last {
+                Some(code) if rsp.more_data => property = code.get() + 1,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Returns a virtual handle not used by any object.
+    fn allocate_handle(&mut self) -> Handle {
+        loop {
+            let handle = self.next_handle;
+            self.next_handle = match handle {
+                VIRTUAL_LAST => VIRTUAL_FIRST,
+                _ => handle + 1,
+            };
+            if !self.entries.contains_key(&handle) {
+                return handle;
+            }
+        }
+    }
+
+    /// Saves the least recently used object (or session) which is loaded
+    /// and not `pinned`. Returns false if there is no such object.
+    fn evict(&mut self, sessions: bool, pinned: &[Handle]) -> Result<bool, Error> {
+        let victim = self
+            .entries
+            .iter()
+            .filter_map(|(&handle, entry)| match entry.state {
+                State::Loaded(physical)
+                    if is_session(handle) == sessions && !pinned.contains(&handle) =>
+                {
+                    Some((entry.last_used, handle, physical))
+                }
+                _ => None,
+            })
+            .min();
+        let Some((_, handle, physical)) = victim else {
+            return Ok(false);
+        };
+
+        let rsp = self.tpm.run(ContextSave {
+            save_handle: physical,
+        })?;
+        let saved = SavedContext::new(&rsp.context);
+        // Saving a session removes it from TPM memory, but an object must
+        // also be flushed.
+        if !sessions {
+            self.tpm.run(FlushContext {
+                flush_handle: physical,
+            })?;
+        }
+        if let Some(entry) = self.entries.get_mut(&handle) {
+            entry.state = State::Saved(saved);
+        }
+        Ok(true)
+    }
+
+    /// Makes sure the managed `handle` is loaded, returning its handle in
+    /// the TPM, or `None` if it was not created through this wrapper.
+    fn load(&mut self, handle: Handle, pinned: &[Handle]) -> Result<Option<Handle>, Error> {
+        loop {
+            let saved = match self.entries.get(&handle) {
+                None => return Ok(None),
+                Some(Entry {
+                    state: State::Loaded(physical),
+                    ..
+                }) => return Ok(Some(*physical)),
+                Some(Entry {
+                    state: State::Saved(saved),
+                    ..
+                }) => saved,
+            };
+            let result = self.tpm.run(ContextLoad {
+                context: saved.context(),
+            });
+            match result.map(|rsp| rsp.loaded_handle) {
+                Ok(physical) => {
+                    let state = State::Loaded(physical);
+                    let last_used = self.clock;
+                    self.entries.insert(handle, Entry { state, last_used });
+                    return Ok(Some(physical));
+                }
+                Err(err) => {
+                    let sessions = match err.tpm_error().map(|e| e.get()) {
+                        Some(tpm::rc::OBJECT_MEMORY) => false,
+                        Some(tpm::rc::SESSION_MEMORY) => true,
+                        _ => return Err(err),
+                    };
+                    if !self.evict(sessions, pinned)? {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts tracking a handle returned by the TPM, returning the handle to
+    /// give to the caller.
+    fn insert(&mut self, physical: Handle) -> Handle {
+        let handle = match tpm::ht::of(physical) {
+            tpm::ht::TRANSIENT => self.allocate_handle(),
+            _ if is_session(physical) => physical,
+            _ => return physical,
+        };
+        let state = State::Loaded(physical);
+        let last_used = self.clock;
+        self.entries.insert(handle, Entry { state, last_used });
+        handle
+    }
+
+    /// Replaces the response with just a header containing `rc`.
+    fn set_response(&mut self, rc: u32) {
+        self.rsp.clear();
+        self.rsp
+            .extend_from_slice(&(tpm::ST::NoSessions as u16).to_be_bytes());
+        self.rsp
+            .extend_from_slice(&(ResponseHeader::SIZE as u32).to_be_bytes());
+        self.rsp.extend_from_slice(&rc.to_be_bytes());
+    }
+
+    fn execute(&mut self, cmd_size: usize) -> Result<(), Error> {
+        self.clock += 1;
+        let cmd = &self.cmd[..cmd_size];
+        let code = read_u32(cmd, 6).map(tpm::CC::new);
+        let attrs = match code {
+            Some(code) => self.attributes(code)?,
+            None => None,
+        };
+        let refs = match (code, attrs) {
+            (Some(code), Some(attrs)) => parse_handles(&self.cmd[..cmd_size], code, attrs),
+            _ => None,
+        };
+        let refs = refs.unwrap_or_default();
+
+        // Flushing a saved object doesn't need the TPM
+        if let [r] = &refs[..] {
+            let entry = self.entries.get(&r.handle);
+            if code == Some(tpm::CC::FlushContext)
+                && !is_session(r.handle)
+                && matches!(
+                    entry,
+                    Some(Entry {
+                        state: State::Saved(_),
+                        ..
+                    })
+                )
+            {
+                self.entries.remove(&r.handle);
+                self.set_response(0);
+                return Ok(());
+            }
+        }
+
+        let pinned: Vec<Handle> = refs.iter().map(|r| r.handle).collect();
+        let mut physical = Vec::with_capacity(refs.len());
+        for r in &refs {
+            match self.load(r.handle, &pinned)? {
+                Some(handle) => physical.push(handle),
+                None => {
+                    self.set_response(tpm::rc::HANDLE | r.location);
+                    return Ok(());
+                }
+            }
+        }
+
+        let rc = loop {
+            let buf = self.tpm.command_buf();
+            buf[..cmd_size].copy_from_slice(&self.cmd[..cmd_size]);
+            for (r, handle) in refs.iter().zip(&physical) {
+                buf[r.offset..r.offset + 4].copy_from_slice(&handle.to_be_bytes());
+            }
+            self.tpm.execute_command(cmd_size as u32)?;
+
+            let rc = read_u32(self.tpm.response_buf(), 6);
+            let sessions = match rc {
+                Some(tpm::rc::OBJECT_MEMORY) => false,
+                Some(tpm::rc::SESSION_MEMORY) => true,
+                _ => break rc,
+            };
+            if !self.evict(sessions, &pinned)? {
+                break rc;
+            }
+        };
+        self.rsp.clear();
+        self.rsp.extend_from_slice(self.tpm.response_buf());
+
+        for handle in &pinned {
+            if let Some(entry) = self.entries.get_mut(handle) {
+                entry.last_used = self.clock;
+            }
+        }
+        if rc != Some(0) {
+            return Ok(());
+        }
+        for r in refs.iter().filter(|r| r.flush) {
+            self.entries.remove(&r.handle);
+        }
+        if attrs.is_some_and(|a| a.contains(tpma::Cc::R_HANDLE)) {
+            if let Some(physical) = read_u32(&self.rsp, ResponseHeader::SIZE) {
+                let handle = self.insert(physical);
+                self.rsp[ResponseHeader::SIZE..][..4].copy_from_slice(&handle.to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Tpm> Tpm for ResourceManager<T> {
+    #[inline]
+    fn command_buf(&mut self) -> &mut [u8] {
+        &mut self.cmd
+    }
+    #[inline]
+    fn response_buf(&self) -> &[u8] {
+        &self.rsp
+    }
+
+    /// Errors returned by the TPM while loading or saving contexts are
+    /// returned as the response to the command.
+    fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
+        match self.execute(cmd_size.to_usize()).map_err(Error::into_kind) {
+            Ok(()) => Ok(()),
+            Err(ErrorKind::Tpm(e)) => {
+                self.set_response(e.get());
+                Ok(())
+            }
+            Err(ErrorKind::Driver(e)) => Err(e),
+            Err(_) => Err(DriverError::InvalidResponse),
+        }
+    }
+
+    #[inline]
+    fn should_retry(
+        &mut self,
+        code: tpm::CC,
+        err: crate::error::TpmError,
+        attempt: u32,
+    ) -> Result<bool, Error> {
+        self.tpm.should_retry(code, err, attempt)
+    }
+}
+
+impl<T: Tpm> Drop for ResourceManager<T> {
+    fn drop(&mut self) {
+        for (handle, entry) in core::mem::take(&mut self.entries) {
+            let flush_handle = match entry.state {
+                State::Loaded(physical) => physical,
+                // Saved sessions still have to be flushed
+                State::Saved(_) if is_session(handle) => handle,
+                State::Saved(_) => continue,
+            };
+            // There's no way to report errors here, and a TPM reset will
+            // flush everything anyway.
+            let _ = self.tpm.run(FlushContext { flush_handle });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        commands::Startup,
+        test::{Respond, ScriptTpm},
+    };
+
+    /// The state of a [`fake_tpm`], which has room for two objects and one
+    /// session.
+    #[derive(Debug, Default)]
+    struct FakeState {
+        /// Loaded objects, as (handle, id)
+        objects: Vec<(Handle, u32)>,
+        /// Sessions, as (handle, loaded)
+        sessions: Vec<(Handle, bool)>,
+        next: u32,
+        commands: usize,
+        /// If TPM2_Startup has not been run yet
+        unstarted: bool,
+    }
+
+    impl FakeState {
+        /// Runs `code`, returning the response code and body.
+        fn execute(&mut self, code: tpm::CC, cmd: &[u8]) -> (u32, Vec<u8>) {
+            self.commands += 1;
+            let arg = read_u32(cmd, CommandHeader::SIZE).unwrap_or(0);
+            let session_loaded = self.sessions.iter().any(|&(_, loaded)| loaded);
+            let mut body = Vec::new();
+            let rc = match code {
+                tpm::CC::Startup => {
+                    self.unstarted = false;
+                    0
+                }
+                _ if self.unstarted => tpm::rc::INITIALIZE,
+                tpm::CC::GetCapability => {
+                    use tpm::CC;
+                    let (more, list) = match read_u32(cmd, 14).unwrap() {
+                        0 => (1, [CC::CreatePrimary, CC::ContextLoad, CC::ContextSave]),
+                        _ => (0, [CC::FlushContext, CC::ReadPublic, CC::StartAuthSession]),
+                    };
+                    body.push(more);
+                    body.extend_from_slice(&(tpm::Cap::Commands as u32).to_be_bytes());
+                    body.extend_from_slice(&(list.len() as u32).to_be_bytes());
+                    for cc in list {
+                        body.extend_from_slice(&cc.attributes().unwrap().bits().to_be_bytes());
+                    }
+                    0
+                }
+                tpm::CC::CreatePrimary if self.objects.len() == 2 => tpm::rc::OBJECT_MEMORY,
+                tpm::CC::StartAuthSession if session_loaded => tpm::rc::SESSION_MEMORY,
+                tpm::CC::CreatePrimary => {
+                    self.next += 1;
+                    let handle = 0x8000_0000 + self.next;
+                    self.objects.push((handle, self.next));
+                    body.extend_from_slice(&handle.to_be_bytes());
+                    0
+                }
+                tpm::CC::StartAuthSession => {
+                    self.next += 1;
+                    let handle = 0x0200_0000 + self.next;
+                    self.sessions.push((handle, true));
+                    body.extend_from_slice(&handle.to_be_bytes());
+                    0
+                }
+                tpm::CC::ReadPublic => {
+                    let session = read_u32(cmd, 18);
+                    match self.objects.iter().find(|&&(h, _)| h == arg) {
+                        _ if session.is_some_and(|s| !self.sessions.contains(&(s, true))) => {
+                            tpm::rc::VALUE | tpm::rc::S | 1 << 8
+                        }
+                        Some((_, id)) => {
+                            body.extend_from_slice(&id.to_be_bytes());
+                            0
+                        }
+                        None => tpm::rc::HANDLE | 1 << 8,
+                    }
+                }
+                tpm::CC::ContextSave => {
+                    let blob = if let Some(&(_, id)) = self.objects.iter().find(|o| o.0 == arg) {
+                        id
+                    } else if let Some(s) = self.sessions.iter_mut().find(|s| **s == (arg, true)) {
+                        s.1 = false;
+                        arg
+                    } else {
+                        return (tpm::rc::HANDLE | 1 << 8, body);
+                    };
+                    body.extend_from_slice(&[0; 8]);
+                    body.extend_from_slice(&arg.to_be_bytes());
+                    body.extend_from_slice(&tpm::rh::NULL.to_be_bytes());
+                    body.extend_from_slice(&[0, 4]);
+                    body.extend_from_slice(&blob.to_be_bytes());
+                    0
+                }
+                tpm::CC::ContextLoad => {
+                    let saved = read_u32(cmd, CommandHeader::SIZE + 8).unwrap();
+                    let blob = read_u32(cmd, CommandHeader::SIZE + 18).unwrap();
+                    let handle = if is_session(saved) {
+                        if session_loaded {
+                            return (tpm::rc::SESSION_MEMORY, body);
+                        }
+                        let s = self.sessions.iter_mut().find(|s| s.0 == saved).unwrap();
+                        s.1 = true;
+                        saved
+                    } else {
+                        if self.objects.len() == 2 {
+                            return (tpm::rc::OBJECT_MEMORY, body);
+                        }
+                        self.next += 1;
+                        let handle = 0x8000_0000 + self.next;
+                        self.objects.push((handle, blob));
+                        handle
+                    };
+                    body.extend_from_slice(&handle.to_be_bytes());
+                    0
+                }
+                tpm::CC::FlushContext => {
+                    let (objects, sessions) = (self.objects.len(), self.sessions.len());
+                    self.objects.retain(|o| o.0 != arg);
+                    self.sessions.retain(|s| s.0 != arg);
+                    if (objects, sessions) == (self.objects.len(), self.sessions.len()) {
+                        tpm::rc::HANDLE | tpm::rc::P | 1 << 8
+                    } else {
+                        0
+                    }
+                }
+                _ => tpm::rc::COMMAND_CODE,
+            };
+            (rc, body)
+        }
+    }
+
+    /// Fake TPM running each command on `state`
+    fn fake_tpm(state: &RefCell<FakeState>) -> ScriptTpm<impl Respond + '_> {
+        ScriptTpm::raw(|cmd: &[u8], rsp: &mut Vec<u8>| {
+            let code = tpm::CC::new(read_u32(cmd, 6).unwrap());
+            let (rc, body) = state.borrow_mut().execute(code, cmd);
+            *rsp = [0x80, 0x01].to_vec();
+            rsp.extend_from_slice(&(10 + body.len() as u32).to_be_bytes());
+            rsp.extend_from_slice(&rc.to_be_bytes());
+            rsp.extend_from_slice(&body);
+        })
+    }
+
+    /// Runs `code` with `handles` (and optionally a session), returning the
+    /// response code and body.
+    fn raw(
+        tpm: &mut impl Tpm,
+        code: tpm::CC,
+        handles: &[Handle],
+        session: Option<Handle>,
+    ) -> (u32, Vec<u8>) {
+        let mut cmd = Vec::new();
+        let tag = match session {
+            Some(_) => tpm::ST::Sessions,
+            None => tpm::ST::NoSessions,
+        };
+        cmd.extend_from_slice(&(tag as u16).to_be_bytes());
+        cmd.extend_from_slice(&[0; 4]);
+        cmd.extend_from_slice(&code.get().to_be_bytes());
+        handles
+            .iter()
+            .for_each(|h| cmd.extend_from_slice(&h.to_be_bytes()));
+        if let Some(session) = session {
+            cmd.extend_from_slice(&9u32.to_be_bytes());
+            cmd.extend_from_slice(&session.to_be_bytes());
+            cmd.extend_from_slice(&[0, 0, CONTINUE_SESSION, 0, 0]);
+        }
+        let len = cmd.len() as u32;
+        cmd[2..6].copy_from_slice(&len.to_be_bytes());
+
+        tpm.command_buf()[..cmd.len()].copy_from_slice(&cmd);
+        tpm.execute_command(len).unwrap();
+        let rsp = tpm.response_buf();
+        (
+            read_u32(rsp, 6).unwrap(),
+            rsp[ResponseHeader::SIZE..].to_vec(),
+        )
+    }
+
+    #[test]
+    fn swap_objects() {
+        let state = RefCell::default();
+        let mut rm = ResourceManager::new(fake_tpm(&state));
+        let handles: Vec<Handle> = (0..4)
+            .map(|_| {
+                let (rc, body) = raw(&mut rm, tpm::CC::CreatePrimary, &[tpm::rh::OWNER], None);
+                assert_eq!(rc, 0);
+                read_u32(&body, 0).unwrap()
+            })
+            .collect();
+        assert_eq!(handles, [0x80FF0000, 0x80FF0001, 0x80FF0002, 0x80FF0003]);
+        assert_eq!(state.borrow().objects.len(), 2);
+
+        // Each object is reloaded (with a new handle) when it is used
+        for (&handle, id) in handles.iter().zip([1, 2, 3, 4]).rev() {
+            let (rc, body) = raw(&mut rm, tpm::CC::ReadPublic, &[handle], None);
+            assert_eq!(rc, 0);
+            assert_eq!(read_u32(&body, 0), Some(id));
+        }
+
+        // Other transient handles are rejected without reaching the TPM
+        let physical = state.borrow().objects[0].0;
+        let commands = state.borrow().commands;
+        let (rc, _) = raw(&mut rm, tpm::CC::ReadPublic, &[physical], None);
+        assert_eq!(rc, tpm::rc::HANDLE | 1 << 8);
+        assert_eq!(state.borrow().commands, commands);
+
+        for &flush_handle in &handles {
+            rm.run(FlushContext { flush_handle }).unwrap();
+        }
+        assert!(state.borrow().objects.is_empty());
+        let (rc, _) = raw(&mut rm, tpm::CC::ReadPublic, &[handles[0]], None);
+        assert_eq!(rc, tpm::rc::HANDLE | 1 << 8);
+        let err = rm.run(FlushContext {
+            flush_handle: handles[0],
+        });
+        let rc = err.unwrap_err().tpm_error().unwrap().get();
+        assert_eq!(rc, tpm::rc::HANDLE | tpm::rc::P | 1 << 8);
+    }
+
+    #[test]
+    fn swap_sessions() {
+        let state = RefCell::default();
+        let mut rm = ResourceManager::new(fake_tpm(&state));
+        let mut start_session = || {
+            let handles = [tpm::rh::NULL, tpm::rh::NULL];
+            let (rc, body) = raw(&mut rm, tpm::CC::StartAuthSession, &handles, None);
+            assert_eq!(rc, 0);
+            read_u32(&body, 0).unwrap()
+        };
+        let s1 = start_session();
+        let s2 = start_session();
+        assert_eq!(state.borrow().sessions, [(s1, false), (s2, true)]);
+
+        let (rc, body) = raw(&mut rm, tpm::CC::CreatePrimary, &[tpm::rh::OWNER], None);
+        assert_eq!(rc, 0);
+        let object = read_u32(&body, 0).unwrap();
+        let (rc, _) = raw(&mut rm, tpm::CC::ReadPublic, &[object], Some(s1));
+        assert_eq!(rc, 0);
+        assert_eq!(state.borrow().sessions, [(s1, true), (s2, false)]);
+
+        // Dropping flushes loaded objects, and both loaded and saved sessions
+        drop(rm);
+        assert!(state.borrow().objects.is_empty());
+        assert!(state.borrow().sessions.is_empty());
+    }
+
+    #[test]
+    fn startup() {
+        let state = RefCell::new(FakeState {
+            unstarted: true,
+            ..Default::default()
+        });
+        let mut rm = ResourceManager::new(fake_tpm(&state));
+        let (rc, _) = raw(&mut rm, tpm::CC::CreatePrimary, &[tpm::rh::OWNER], None);
+        assert_eq!(rc, tpm::rc::INITIALIZE);
+
+        rm.run(Startup {
+            startup_type: tpm::SU::Clear,
+        })
+        .unwrap();
+        assert!(!state.borrow().unstarted);
+
+        // The command attributes are read once the TPM has been started
+        let (rc, body) = raw(&mut rm, tpm::CC::CreatePrimary, &[tpm::rh::OWNER], None);
+        assert_eq!(rc, 0);
+        assert_eq!(read_u32(&body, 0), Some(VIRTUAL_FIRST));
+        assert!(!rm.commands.is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    cmp::Reverse,
+    prelude::v1::*,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    error::{DriverError, TpmError},
+    types::tpm,
+    Auths, Command, Error, Tpm, TpmRun,
+};
+
+/// A TPM which can be shared between threads
+///
+/// Cloning a `SharedTpm` gives another handle to the same TPM. Commands from
+/// all handles are run one at a time: callers wait in a queue, ordered by
+/// [priority](SharedTpm::priority) and then by arrival. Single commands can
+/// be run with [`SharedTpm::run`], while [`SharedTpm::lock`] gives exclusive
+/// access for a sequence of commands.
+///
+/// So that a steady stream of high-priority callers cannot starve the rest,
+/// a waiting caller's priority is raised by one each time it has been passed
+/// over 8 times.
+#[derive(Clone, Debug)]
+pub struct SharedTpm {
+    shared: Arc<Shared>,
+    priority: u8,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    turn: Condvar,
+    tpm: Mutex<Box<dyn Tpm + Send>>,
+}
+
+impl core::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Shared").finish_non_exhaustive()
+    }
+}
+
+/// How many times a caller waiting on a [`SharedTpm`] can be passed over
+/// before its priority is raised
+const AGING_PASSES: u32 = 8;
+
+#[derive(Default)]
+struct Queue {
+    waiting: Vec<Waiter>,
+    next_ticket: u64,
+    busy: bool,
+}
+
+struct Waiter {
+    priority: u8,
+    ticket: u64,
+    passes: u32,
+}
+
+impl Queue {
+    /// Adds a caller to the queue, returning its ticket.
+    fn join(&mut self, priority: u8) -> u64 {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        self.waiting.push(Waiter {
+            priority,
+            ticket,
+            passes: 0,
+        });
+        ticket
+    }
+
+    /// Returns the ticket of the next caller: highest priority first, then in
+    /// order of arrival.
+    fn next(&self) -> Option<u64> {
+        let w = self
+            .waiting
+            .iter()
+            .max_by_key(|w| (w.priority, Reverse(w.ticket)))?;
+        Some(w.ticket)
+    }
+
+    /// Removes `ticket` from the queue, aging the callers it was chosen over.
+    fn take(&mut self, ticket: u64) {
+        self.waiting.retain(|w| w.ticket != ticket);
+        for w in &mut self.waiting {
+            w.passes += 1;
+            if w.passes % AGING_PASSES == 0 {
+                w.priority = w.priority.saturating_add(1);
+            }
+        }
+    }
+}
+
+/// Ignores poisoning, as a panicking caller cannot leave the TPM (or the
+/// queue) in an inconsistent state.
+fn lock<T: ?Sized>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl SharedTpm {
+    /// Shares `tpm`, with a default priority of zero.
+    pub fn new(tpm: impl Tpm + Send + 'static) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(Queue::default()),
+                turn: Condvar::new(),
+                tpm: Mutex::new(Box::new(tpm)),
+            }),
+            priority: 0,
+        }
+    }
+
+    /// Returns a handle to the same TPM whose commands run before those of
+    /// lower-priority handles waiting in the queue (subject to aging, see
+    /// [`SharedTpm`]).
+    pub fn priority(&self, priority: u8) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            priority,
+        }
+    }
+
+    /// Waits for exclusive access to the TPM.
+    pub fn lock(&self) -> SharedTpmGuard<'_> {
+        let mut queue = lock(&self.shared.queue);
+        let ticket = queue.join(self.priority);
+        while queue.busy || queue.next() != Some(ticket) {
+            queue = self
+                .shared
+                .turn
+                .wait(queue)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        queue.take(ticket);
+        queue.busy = true;
+        drop(queue);
+
+        SharedTpmGuard {
+            shared: &self.shared,
+            tpm: lock(&self.shared.tpm),
+        }
+    }
+
+    /// Runs a single command, converting the response (which borrows from
+    /// the TPM) into an owned value with `f`.
+    pub fn run<C: Command + Auths<N>, const N: usize, R>(
+        &self,
+        cmd: C,
+        f: impl FnOnce(C::Response<'_>) -> R,
+    ) -> Result<R, Error> {
+        let mut guard = self.lock();
+        let rsp = guard.run(cmd)?;
+        Ok(f(rsp))
+    }
+
+    #[cfg(test)]
+    fn waiting(&self) -> usize {
+        lock(&self.shared.queue).waiting.len()
+    }
+}
+
+/// Exclusive access to a [`SharedTpm`], returned by [`SharedTpm::lock`]
+///
+/// The next caller in the queue is given access when this is dropped.
+pub struct SharedTpmGuard<'a> {
+    shared: &'a Shared,
+    tpm: MutexGuard<'a, Box<dyn Tpm + Send>>,
+}
+
+impl core::fmt::Debug for SharedTpmGuard<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedTpmGuard").finish_non_exhaustive()
+    }
+}
+
+impl Drop for SharedTpmGuard<'_> {
+    fn drop(&mut self) {
+        lock(&self.shared.queue).busy = false;
+        self.shared.turn.notify_all();
+    }
+}
+
+impl Tpm for SharedTpmGuard<'_> {
+    #[inline]
+    fn command_buf(&mut self) -> &mut [u8] {
+        self.tpm.command_buf()
+    }
+    #[inline]
+    fn response_buf(&self) -> &[u8] {
+        self.tpm.response_buf()
+    }
+    #[inline]
+    fn execute_command(&mut self, cmd_size: u32) -> Result<(), DriverError> {
+        self.tpm.execute_command(cmd_size)
+    }
+    #[inline]
+    fn should_retry(&mut self, code: tpm::CC, err: TpmError, attempt: u32) -> Result<bool, Error> {
+        self.tpm.should_retry(code, err, attempt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{thread, vec::Vec};
+
+    use super::*;
+    use crate::{commands::GetRandom, wrap::test::sequence_tpm};
+
+    #[test]
+    fn shared_tpm() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedTpm>();
+
+        let tpm = SharedTpm::new(sequence_tpm(&[]));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let tpm = tpm.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        let cmd = GetRandom { bytes_requested: 0 };
+                        let len = tpm.run(cmd, |rsp| rsp.random_bytes.len()).unwrap();
+                        assert_eq!(len, 0);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(tpm.waiting(), 0);
+    }
+
+    #[test]
+    fn priority_order() {
+        let tpm = SharedTpm::new(sequence_tpm(&[]));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let guard = tpm.lock();
+        let threads: Vec<_> = [1, 3, 1, 2]
+            .into_iter()
+            .enumerate()
+            .map(|(i, priority)| {
+                let handle = tpm.priority(priority);
+                let order = order.clone();
+                let t = thread::spawn(move || {
+                    let _guard = handle.lock();
+                    order.lock().unwrap().push(i);
+                });
+                // Wait for the thread to join the queue, so arrival order is fixed
+                while tpm.waiting() <= i {
+                    thread::yield_now();
+                }
+                t
+            })
+            .collect();
+        drop(guard);
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), [1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn priority_aging() {
+        let mut queue = Queue::default();
+        let low = queue.join(0);
+        let mut passes = 0;
+        loop {
+            let high = queue.join(1);
+            let next = queue.next().unwrap();
+            queue.take(next);
+            if next == low {
+                break;
+            }
+            assert_eq!(next, high);
+            passes += 1;
+        }
+        // Raised to priority 1, the oldest caller goes first.
+        assert_eq!(passes, AGING_PASSES);
+        assert_eq!(queue.waiting.len(), 1);
+    }
+}